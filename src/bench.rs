@@ -0,0 +1,48 @@
+use crate::GenerationParams;
+use crate::terrain::TerrainGenerator;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+// Fixed workload so results are comparable between versions
+const SIZES: [u32; 3] = [128, 256, 512];
+const SEEDS: [u64; 3] = [1, 2, 3];
+
+#[derive(Serialize)]
+struct BenchResult {
+    width: u32,
+    height: u32,
+    seed: u64,
+    stages: BTreeMap<&'static str, f64>,
+    total_seconds: f64,
+    cells_per_second: f64,
+}
+
+/// Run the standard benchmark and print one JSON object per run to stdout.
+pub fn run() {
+    for &size in &SIZES {
+        for &seed in &SEEDS {
+            let mut generator = TerrainGenerator::new(size, size, GenerationParams {
+                seed,
+                ..GenerationParams::default()
+            });
+            
+            let start = Instant::now();
+            generator.generate();
+            let total_seconds = start.elapsed().as_secs_f64();
+            
+            let result = BenchResult {
+                width: size,
+                height: size,
+                seed,
+                stages: generator.timings().iter()
+                    .map(|timing| (timing.stage, timing.seconds))
+                    .collect(),
+                total_seconds,
+                cells_per_second: (size * size) as f64 / total_seconds,
+            };
+            
+            println!("{}", serde_json::to_string(&result).expect("Failed to serialize bench result"));
+        }
+    }
+}
\ No newline at end of file
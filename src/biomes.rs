@@ -1,73 +1,342 @@
-use crate::{TerrainCell, BiomeType};
+use crate::{TerrainCell, BiomeType, GenerationParams, Topology, max_slope, coast_distances, percentile_of_sorted, sorted_elevations};
+use crate::climate::LAPSE_RATE;
+use serde::{Deserialize, Serialize};
 
-pub struct BiomeAssigner;
+// Mean temperature below which trees give way to alpine tundra
+const TREE_LINE_TEMPERATURE: f32 = 5.0;
+
+// Extra cooling, in degrees C, applied at the pole itself by the polar bias
+const POLAR_COOLING: f32 = 30.0;
+
+// How far climate is probed along each axis for a runner-up biome, and in how
+// many steps: degrees C, rainfall units and elevation units
+const BLEND_TEMPERATURE: f32 = 5.0;
+const BLEND_RAINFALL: f32 = 2.0;
+const BLEND_ELEVATION: f32 = 0.1;
+const BLEND_STEPS: usize = 5;
+
+// Weighted excess over a biome's preferred ground tolerated before it gives way
+const ELEVATION_TOLERANCE: f32 = 0.25;
+
+// Elevation units of excess that one unit of slope counts as
+const SLOPE_WEIGHT: f32 = 2.0;
+
+// Slope at which a shore is too steep for any beach; flatter shores get a
+// proportionally larger share of the configured beach width
+const BEACH_MAX_SLOPE: f32 = 0.02;
+
+/// Highest ground a biome favours, and the hardier biome that takes over
+/// when a cell is too high or steep for it.
+fn elevation_preference(biome: BiomeType) -> Option<(f32, BiomeType)> {
+    match biome {
+        BiomeType::Rainforest => Some((1.0, BiomeType::Forest)),
+        BiomeType::Forest => Some((1.6, BiomeType::Grassland)),
+        BiomeType::Desert => Some((1.4, BiomeType::Grassland)),
+        _ => None,
+    }
+}
+
+/// One entry of the climate-to-biome table. A rule matches when every bound it
+/// sets holds; bounds are exclusive and unset bounds always pass. Rules are
+/// tried in order and the first match wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BiomeRule {
+    pub biome: BiomeType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_rainfall: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rainfall: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_elevation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_elevation: Option<f32>,
+}
+
+impl BiomeRule {
+    /// Whether the cell's climate lies within every bound this rule sets.
+    pub fn matches(&self, cell: &TerrainCell) -> bool {
+        let within = |value: f32, min: Option<f32>, max: Option<f32>| {
+            min.is_none_or(|min| value > min) && max.is_none_or(|max| value < max)
+        };
+        
+        within(cell.temperature, self.min_temperature, self.max_temperature)
+            && within(cell.rainfall, self.min_rainfall, self.max_rainfall)
+            && within(cell.elevation, self.min_elevation, self.max_elevation)
+    }
+}
+
+/// The built-in climate table, used when no rules file is given.
+pub fn default_biome_rules() -> Vec<BiomeRule> {
+    let rule = |biome| BiomeRule {
+        biome,
+        min_temperature: None,
+        max_temperature: None,
+        min_rainfall: None,
+        max_rainfall: None,
+        min_elevation: None,
+        max_elevation: None,
+    };
+    
+    vec![
+        BiomeRule { max_temperature: Some(-5.0), ..rule(BiomeType::Tundra) },
+        BiomeRule { max_rainfall: Some(1.5), min_temperature: Some(25.0), ..rule(BiomeType::Desert) },
+        BiomeRule { max_rainfall: Some(1.5), min_temperature: Some(10.0), ..rule(BiomeType::Grassland) },
+        BiomeRule { max_rainfall: Some(1.5), ..rule(BiomeType::Tundra) },
+        BiomeRule { min_rainfall: Some(12.0), min_temperature: Some(22.0), ..rule(BiomeType::Rainforest) },
+        BiomeRule { min_rainfall: Some(6.0), min_temperature: Some(5.0), ..rule(BiomeType::Forest) },
+        BiomeRule { min_rainfall: Some(3.0), ..rule(BiomeType::Grassland) },
+        BiomeRule { min_temperature: Some(15.0), ..rule(BiomeType::Grassland) },
+        rule(BiomeType::Tundra),
+    ]
+}
+
+/// Read an ordered list of biome rules from a JSON file.
+#[cfg(feature = "fs")]
+pub fn load_biome_rules(filename: &str) -> Result<Vec<BiomeRule>, Box<dyn std::error::Error>> {
+    let rules: Vec<BiomeRule> = serde_json::from_str(&std::fs::read_to_string(filename)?)?;
+    if rules.is_empty() {
+        return Err(format!("{}: no biome rules", filename).into());
+    }
+    Ok(rules)
+}
+
+/// Elevations, one per row, at which the mean climate of that latitude crosses
+/// freezing (snow line) and the tree-growth threshold (tree line).
+#[derive(Clone, Default)]
+pub struct AltitudeLines {
+    pub snow_line: Vec<f32>,
+    pub tree_line: Vec<f32>,
+}
+
+pub struct BiomeAssigner {
+    sea_ice_temperature: f32,
+    topology: Topology,
+    polar_bias: f32,
+    elevation_weight: f32,
+    mountain_elevation: f32,
+    mountain_percentile: f32,
+    smoothing_passes: usize,
+    beaches: bool,
+    beach_width: u32,
+    rules: Vec<BiomeRule>,
+}
 
 impl BiomeAssigner {
-    pub fn new() -> Self {
-        Self
+    pub fn new(params: &GenerationParams) -> Self {
+        Self {
+            sea_ice_temperature: params.sea_ice_temperature,
+            topology: params.topology,
+            polar_bias: params.polar_bias,
+            elevation_weight: params.biome_elevation_weight.max(0.0),
+            mountain_elevation: params.mountain_elevation,
+            mountain_percentile: params.mountain_percentile,
+            smoothing_passes: params.biome_smoothing_passes,
+            beaches: !params.no_beaches,
+            beach_width: params.beach_width,
+            rules: params.biome_rules.clone().unwrap_or_else(default_biome_rules),
+        }
+    }
+    
+    /// Derive snow and tree lines from the temperature field. Each row's
+    /// sea-level temperature is the mean of its land cells with lapse-rate
+    /// cooling removed; the lines are where that temperature, cooled with
+    /// altitude, reaches freezing and the tree-line temperature. The snow line
+    /// never drops below land that is above freezing, so a row glaciates
+    /// wholesale only if its land is frozen. Rows without land take the lines
+    /// of the nearest row with some.
+    pub fn altitude_lines(&self, cells: &[Vec<TerrainCell>]) -> AltitudeLines {
+        let lines: Vec<Option<(f32, f32)>> = cells.iter().map(|row| {
+            let land: Vec<&TerrainCell> = row.iter().filter(|cell| !cell.is_water).collect();
+            if land.is_empty() {
+                return None;
+            }
+            let sea_level_temperature = land.iter()
+                .map(|cell| cell.temperature + cell.elevation * LAPSE_RATE)
+                .sum::<f32>() / land.len() as f32;
+            let thawed = land.iter()
+                .filter(|cell| cell.temperature > 0.0)
+                .map(|cell| cell.elevation)
+                .fold(0.0, f32::max);
+            
+            Some((
+                (sea_level_temperature / LAPSE_RATE).max(thawed),
+                ((sea_level_temperature - TREE_LINE_TEMPERATURE) / LAPSE_RATE).max(0.0),
+            ))
+        }).collect();
+        
+        let nearest = |y: usize| {
+            (0..cells.len())
+                .filter_map(|other| lines[other].map(|line| (y.abs_diff(other), line)))
+                .min_by_key(|&(distance, _)| distance)
+                .map_or((0.0, 0.0), |(_, line)| line)
+        };
+        let (snow_line, tree_line) = (0..cells.len()).map(|y| lines[y].unwrap_or_else(|| nearest(y))).unzip();
+        
+        AltitudeLines { snow_line, tree_line }
     }
     
-    pub fn assign_biomes(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    pub fn assign_biomes(&self, cells: &mut [Vec<TerrainCell>], lines: &AltitudeLines) {
+        let height = cells.len();
+        
+        // Slopes are only needed to weigh biomes against steep ground
+        let slopes: Option<Vec<Vec<f32>>> = (self.elevation_weight > 0.0).then(|| {
+            (0..height).map(|y| {
+                (0..cells[y].len()).map(|x| max_slope(cells, self.topology, x, y)).collect()
+            }).collect()
+        });
+        
+        let mountain_line = self.mountain_line(cells);
+        
         // First pass: basic biome assignment
-        for row in cells.iter_mut() {
-            for cell in row.iter_mut() {
+        for (y, row) in cells.iter_mut().enumerate() {
+            let polar = self.polar_strength(y, height);
+            for (x, cell) in row.iter_mut().enumerate() {
                 if cell.is_water {
-                    cell.biome = BiomeType::Ocean;
+                    cell.secondary_biome = None;
+                    cell.biome_blend = 0.0;
+                    cell.biome = if cell.temperature < self.sea_ice_temperature {
+                        BiomeType::SeaIce
+                    } else {
+                        BiomeType::Ocean
+                    };
                 } else {
-                    cell.biome = self.determine_biome(cell);
+                    let cutoffs = (lines.snow_line[y], lines.tree_line[y], mountain_line);
+                    cell.biome = self.determine_biome(cell, cutoffs.0, cutoffs.1, cutoffs.2, polar);
+                    let runner_up = self.runner_up(cell, cutoffs, polar);
+                    cell.secondary_biome = runner_up.map(|(biome, _)| biome);
+                    cell.biome_blend = runner_up.map_or(0.0, |(_, blend)| blend);
+                    if let Some(slopes) = &slopes {
+                        cell.biome = self.prefer_elevation(cell.biome, cell.elevation, slopes[y][x]);
+                    }
                 }
             }
         }
         
-        // Second pass: smooth transitions and add special features
-        self.smooth_biome_transitions(cells);
-        self.add_beaches(cells);
+        // Second pass: smooth transitions, each pass working on the last one's
+        // output until nothing changes, then add special features
+        for _ in 0..self.smoothing_passes {
+            if !self.smooth_biome_transitions(cells) {
+                break;
+            }
+        }
+        if self.beaches {
+            self.add_beaches(cells);
+        }
         self.enhance_coastal_features(cells);
+        
+        // Later passes may have moved a cell onto its runner-up
+        for cell in cells.iter_mut().flatten() {
+            if cell.secondary_biome == Some(cell.biome) {
+                cell.secondary_biome = None;
+                cell.biome_blend = 0.0;
+            }
+        }
+    }
+    
+    /// How far row `y` lies into the polar band set by `polar_bias`: 0 at the
+    /// band's equatorward edge (and outside it), rising to 1 at the pole.
+    fn polar_strength(&self, y: usize, height: usize) -> f32 {
+        if self.polar_bias <= 0.0 {
+            return 0.0;
+        }
+        
+        let latitude = ((y as f32 + 0.5) / height as f32 - 0.5).abs() * 2.0;
+        let band = self.polar_bias.min(1.0);
+        ((latitude - (1.0 - band)) / band).max(0.0)
     }
     
-    fn determine_biome(&self, cell: &TerrainCell) -> BiomeType {
+    /// Elevation above which land is Mountain: fixed, or a land percentile.
+    fn mountain_line(&self, cells: &[Vec<TerrainCell>]) -> f32 {
+        if self.mountain_percentile <= 0.0 {
+            return self.mountain_elevation;
+        }
+        let land = sorted_elevations(cells.iter().flatten().filter(|cell| !cell.is_water));
+        percentile_of_sorted(&land, self.mountain_percentile).unwrap_or(self.mountain_elevation)
+    }
+    
+    /// The first different biome reached by nudging the cell's temperature,
+    /// rainfall or elevation either way, up to the `BLEND_*` distances, with a
+    /// blend factor of 0.5 for the smallest nudge falling to 0.1 at full reach.
+    fn runner_up(&self, cell: &TerrainCell, (snow_line, tree_line, mountain_line): (f32, f32, f32),
+                 polar: f32) -> Option<(BiomeType, f32)> {
+        let biome = cell.biome;
+        for step in 1..=BLEND_STEPS {
+            let fraction = step as f32 / BLEND_STEPS as f32;
+            for sign in [-1.0, 1.0] {
+                let nudge = sign * fraction;
+                let probes = [
+                    TerrainCell { temperature: cell.temperature + nudge * BLEND_TEMPERATURE, ..cell.clone() },
+                    TerrainCell { rainfall: cell.rainfall + nudge * BLEND_RAINFALL, ..cell.clone() },
+                    TerrainCell { elevation: cell.elevation + nudge * BLEND_ELEVATION, ..cell.clone() },
+                ];
+                for probe in &probes {
+                    let other = self.determine_biome(probe, snow_line, tree_line, mountain_line, polar);
+                    if other != biome {
+                        let blend = 0.5 * (1.0 - (step - 1) as f32 / BLEND_STEPS as f32);
+                        return Some((other, blend));
+                    }
+                }
+            }
+        }
+        None
+    }
+    
+    fn determine_biome(&self, cell: &TerrainCell, snow_line: f32, tree_line: f32, mountain_line: f32, polar: f32) -> BiomeType {
         let temp = cell.temperature;
-        let rainfall = cell.rainfall;
         let elevation = cell.elevation;
         
         // More nuanced elevation-based biomes
-        if elevation > 2.0 {
-            return BiomeType::Mountain;
+        if elevation > snow_line {
+            return BiomeType::Glacier;
         }
         
-        if elevation > 1.5 && temp < 5.0 {
-            return BiomeType::Tundra;
+        // Inside the polar band cold wins over elevation, so even lowland and
+        // mountain cells become ice cap or tundra
+        if polar > 0.0 {
+            let polar_temp = temp - polar * POLAR_COOLING;
+            if polar_temp < -10.0 {
+                return BiomeType::Glacier;
+            }
+            if polar_temp < 0.0 {
+                return BiomeType::Tundra;
+            }
         }
         
-        if temp < -5.0 {
+        if elevation > mountain_line {
+            return BiomeType::Mountain;
+        }
+        
+        // Alpine tundra above the tree line
+        if elevation > tree_line {
             return BiomeType::Tundra;
         }
         
-        // Improved biome logic with better transitions
-        if rainfall < 1.5 {
-            if temp > 25.0 {
-                BiomeType::Desert
-            } else if temp > 10.0 {
-                BiomeType::Grassland
-            } else {
-                BiomeType::Tundra
-            }
-        } else if rainfall > 12.0 && temp > 22.0 {
-            BiomeType::Rainforest
-        } else if rainfall > 6.0 && temp > 5.0 {
-            BiomeType::Forest
-        } else if rainfall > 3.0 {
-            BiomeType::Grassland
-        } else {
-            if temp > 15.0 {
-                BiomeType::Grassland
-            } else {
-                BiomeType::Tundra
+        // Cells that no rule covers fall back to grassland
+        self.rules.iter()
+            .find(|rule| rule.matches(cell))
+            .map_or(BiomeType::Grassland, |rule| rule.biome)
+    }
+    
+    /// Step a biome down to hardier ones while the cell's height above the
+    /// biome's preferred ground, plus its slope, outweighs the tolerance.
+    fn prefer_elevation(&self, mut biome: BiomeType, elevation: f32, slope: f32) -> BiomeType {
+        while let Some((preferred, fallback)) = elevation_preference(biome) {
+            let excess = (elevation - preferred).max(0.0) + slope * SLOPE_WEIGHT;
+            if excess * self.elevation_weight <= ELEVATION_TOLERANCE {
+                break;
             }
+            biome = fallback;
         }
+        biome
     }
     
-    fn smooth_biome_transitions(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    /// One smoothing pass; returns whether any biome changed.
+    fn smooth_biome_transitions(&self, cells: &mut [Vec<TerrainCell>]) -> bool {
         let height = cells.len();
         let width = cells[0].len();
         let mut new_biomes = vec![vec![BiomeType::Ocean; width]; height];
@@ -88,13 +357,13 @@ impl BiomeAssigner {
                     
                     // If surrounded by different biomes, consider transition
                     let different_neighbors = neighbors.iter()
-                        .filter(|&&biome| biome != current_biome && biome != BiomeType::Ocean)
+                        .filter(|&&biome| biome != current_biome && !biome.is_sea())
                         .count();
                     
-                    if different_neighbors >= 4 {
+                    if different_neighbors >= self.topology.neighbor_count() / 2 {
                         // Find most common non-ocean neighbor biome
                         if let Some(common_biome) = self.most_common_biome(&neighbors) {
-                            if common_biome != BiomeType::Ocean {
+                            if !common_biome.is_sea() {
                                 new_biomes[y][x] = common_biome;
                             }
                         }
@@ -104,108 +373,135 @@ impl BiomeAssigner {
         }
         
         // Apply smoothed biomes (but preserve rivers)
+        let mut changed = false;
         for y in 0..height {
             for x in 0..width {
-                if !cells[y][x].is_water && !cells[y][x].has_river {
+                if !cells[y][x].is_water && !cells[y][x].has_river && cells[y][x].biome != new_biomes[y][x] {
                     cells[y][x].biome = new_biomes[y][x];
+                    changed = true;
                 }
             }
         }
+        changed
     }
     
     fn get_neighbor_biomes(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> Vec<BiomeType> {
-        let mut neighbors = Vec::new();
-        
-        for dy in -1i32..=1 {
-            for dx in -1i32..=1 {
-                if dx == 0 && dy == 0 { continue; }
-                
-                let nx = x as i32 + dx;
-                let ny = y as i32 + dy;
-                
-                if nx >= 0 && nx < cells[0].len() as i32 && ny >= 0 && ny < cells.len() as i32 {
-                    neighbors.push(cells[ny as usize][nx as usize].biome);
-                }
-            }
-        }
-        
-        neighbors
+        self.topology.neighbors(x, y, cells[0].len(), cells.len())
+            .map(|(nx, ny, _)| cells[ny][nx].biome)
+            .collect()
     }
     
+    /// Most frequent land biome; ties go to the biome declared first in
+    /// `BiomeType`, so smoothing is reproducible.
     fn most_common_biome(&self, biomes: &[BiomeType]) -> Option<BiomeType> {
-        use std::collections::HashMap;
-        
-        let mut counts = HashMap::new();
-        for &biome in biomes {
-            *counts.entry(biome).or_insert(0) += 1;
+        let mut counts: Vec<(BiomeType, usize)> = Vec::new();
+        for &biome in biomes.iter().filter(|biome| !biome.is_sea()) {
+            match counts.iter_mut().find(|(seen, _)| *seen == biome) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((biome, 1)),
+            }
         }
         
         counts.into_iter()
-            .filter(|(biome, _)| *biome != BiomeType::Ocean)
-            .max_by_key(|(_, count)| *count)
+            .max_by_key(|&(biome, count)| (count, std::cmp::Reverse(biome as u8)))
             .map(|(biome, _)| biome)
     }
     
-    fn enhance_coastal_features(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    fn enhance_coastal_features(&self, cells: &mut [Vec<TerrainCell>]) {
         let height = cells.len();
         let width = cells[0].len();
         
         for y in 0..height {
             for x in 0..width {
-                if !cells[y][x].is_water && cells[y][x].elevation < 0.4 {
-                    if self.is_adjacent_to_water(x, y, cells) {
-                        // Create more diverse coastal biomes
-                        let temp = cells[y][x].temperature;
-                        let rainfall = cells[y][x].rainfall;
-                        
-                        if temp > 20.0 && rainfall < 3.0 {
-                            cells[y][x].biome = BiomeType::Beach;
-                        } else if temp > 15.0 && rainfall > 8.0 {
-                            // Coastal forest/swamp
-                            cells[y][x].biome = BiomeType::Forest;
-                        } else {
-                            cells[y][x].biome = BiomeType::Beach;
-                        }
+                if !cells[y][x].is_water && cells[y][x].elevation < 0.4
+                    && !Self::is_frozen(&cells[y][x])
+                    && self.is_adjacent_to_water(x, y, cells) {
+                    // Create more diverse coastal biomes. Sloped beaches have
+                    // already decided which shores are sand, and without
+                    // beaches the other shores keep their climate biome.
+                    let temp = cells[y][x].temperature;
+                    let rainfall = cells[y][x].rainfall;
+                    let beach = if !self.beaches || self.beach_width > 0 { cells[y][x].biome } else { BiomeType::Beach };
+                    
+                    if temp > 20.0 && rainfall < 3.0 {
+                        cells[y][x].biome = beach;
+                    } else if temp > 22.0 && rainfall > 10.0 {
+                        // Tidal swamp forest on hot, very wet, low-lying shores
+                        cells[y][x].biome = BiomeType::Mangrove;
+                    } else if temp > 15.0 && rainfall > 8.0 {
+                        // Coastal forest/swamp
+                        cells[y][x].biome = BiomeType::Forest;
+                    } else {
+                        cells[y][x].biome = beach;
                     }
                 }
             }
         }
     }
     
-    fn add_beaches(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    fn add_beaches(&self, cells: &mut [Vec<TerrainCell>]) {
+        if self.beach_width > 0 {
+            self.add_sloped_beaches(cells);
+            return;
+        }
         let height = cells.len();
         let width = cells[0].len();
         
         for y in 0..height {
             for x in 0..width {
-                if !cells[y][x].is_water && cells[y][x].elevation < 0.3 {
-                    if self.is_adjacent_to_water(x, y, cells) {
-                        cells[y][x].biome = BiomeType::Beach;
-                    }
+                if !cells[y][x].is_water && cells[y][x].elevation < 0.3
+                    && !Self::is_frozen(&cells[y][x])
+                    && self.is_adjacent_to_water(x, y, cells) {
+                    cells[y][x].biome = BiomeType::Beach;
                 }
             }
         }
     }
     
-    fn is_adjacent_to_water(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> bool {
-        let height = cells.len();
-        let width = cells[0].len();
+    /// Beaches that reach inland as far as the shore's gentleness allows: a
+    /// land cell is beach while its distance from the coast is within
+    /// `beach_width` scaled down by its slope. Beaches grow inland from the
+    /// water one coast distance at a time, so a cell only becomes beach next
+    /// to beach one step nearer the coast and a cliff stops the sand behind it.
+    fn add_sloped_beaches(&self, cells: &mut [Vec<TerrainCell>]) {
+        let (width, height) = (cells[0].len(), cells.len());
+        let distances = coast_distances(cells, self.topology);
+        let slopes: Vec<Vec<f32>> = (0..height).map(|y| {
+            (0..width).map(|x| max_slope(cells, self.topology, x, y)).collect()
+        }).collect();
         
-        for dy in -1i32..=1 {
-            for dx in -1i32..=1 {
-                if dx == 0 && dy == 0 { continue; }
-                
-                let nx = x as i32 + dx;
-                let ny = y as i32 + dy;
-                
-                if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
-                    if cells[ny as usize][nx as usize].is_water {
-                        return true;
+        let mut beach = vec![vec![false; width]; height];
+        for distance in 1..=self.beach_width {
+            for y in 0..height {
+                for x in 0..width {
+                    let cell = &cells[y][x];
+                    if distances[y][x] != distance || cell.is_water || Self::is_frozen(cell) {
+                        continue;
                     }
+                    let reach = self.beach_width as f32 * (1.0 - slopes[y][x] / BEACH_MAX_SLOPE);
+                    let shoreward = distance == 1 || self.topology.neighbors(x, y, width, height)
+                        .any(|(nx, ny, _)| beach[ny][nx] && distances[ny][nx] == distance - 1);
+                    beach[y][x] = shoreward && distance as f32 <= reach.round();
                 }
             }
         }
         
-        false
+        for (row, beach_row) in cells.iter_mut().zip(beach) {
+            for (cell, beach) in row.iter_mut().zip(beach_row) {
+                if beach {
+                    cell.biome = BiomeType::Beach;
+                }
+            }
+        }
+    }
+    
+    /// Frozen shores keep their tundra or ice rather than becoming beach.
+    fn is_frozen(cell: &TerrainCell) -> bool {
+        cell.temperature < 0.0 || matches!(cell.biome, BiomeType::Tundra | BiomeType::Glacier)
+    }
+    
+    fn is_adjacent_to_water(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> bool {
+        self.topology.neighbors(x, y, cells[0].len(), cells.len())
+            .any(|(nx, ny, _)| cells[ny][nx].is_water)
     }
 }
\ No newline at end of file
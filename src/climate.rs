@@ -1,58 +1,367 @@
-use crate::TerrainCell;
+use crate::{TerrainCell, FlowField, GenerationParams, Projection, Topology, WindModel, nearest_coast};
+use crate::seed;
+use noise::{NoiseFn, Perlin};
+
+/// Temperature drop in degrees C per unit of elevation.
+pub const LAPSE_RATE: f32 = 6.5;
+
+/// Distance inland, in full-resolution cells, over which water moderates temperature.
+const MARITIME_RANGE: f32 = 20.0;
 
 pub struct ClimateSimulator {
     width: u32,
     height: u32,
+    world_scale: f32,
+    projection: Projection,
+    topology: Topology,
+    wind_model: WindModel,
+    temperature_noise: f32,
+    temperature_perlin: Perlin,
+    rainfall_noise: f32,
+    rainfall_perlin: Perlin,
+    temperature_spread: f32,
+    rainfall_spread: f32,
+    axial_offset: f32,
+    convection_threshold: f32,
+    convection_strength: f32,
+    maritime_moderation: f32,
+    ocean_mixing: usize,
+    land_retention: f32,
 }
 
 impl ClimateSimulator {
-    pub fn new(width: u32, height: u32) -> Self {
-        Self { width, height }
+    pub fn new(width: u32, height: u32, world_scale: f32, params: &GenerationParams) -> Self {
+        Self {
+            width,
+            height,
+            world_scale,
+            projection: params.projection,
+            topology: params.topology,
+            wind_model: params.wind_model,
+            temperature_noise: params.temperature_noise,
+            temperature_perlin: Perlin::new(seed::derive_seed(params.seed, seed::STREAM_TEMPERATURE_NOISE) as u32),
+            rainfall_noise: params.rainfall_noise,
+            rainfall_perlin: Perlin::new(seed::derive_seed(params.seed, seed::STREAM_RAINFALL_NOISE) as u32),
+            temperature_spread: params.temperature_spread,
+            rainfall_spread: params.rainfall_spread,
+            axial_offset: params.axial_offset,
+            convection_threshold: params.convection_threshold,
+            convection_strength: params.convection_strength,
+            maritime_moderation: params.maritime_moderation.clamp(0.0, 1.0),
+            ocean_mixing: params.ocean_mixing,
+            // One step downwind covers `world_scale` world units
+            land_retention: if params.moisture_range > 0.0 {
+                (-world_scale / params.moisture_range).exp()
+            } else {
+                1.0
+            },
+        }
     }
     
-    pub fn simulate(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    /// Lapse-rate temperature for every cell. Runs before water is placed,
+    /// since glacial carving reads it; water cells are corrected afterwards by
+    /// `set_water_temperatures`.
+    pub fn simulate_temperature(&self, cells: &mut [Vec<TerrainCell>]) {
         self.calculate_temperature(cells);
-        self.simulate_prevailing_winds(cells);
+    }
+    
+    /// Rainfall, winds and rain shadows. The wind runs after local rainfall so
+    /// it has rain to carry off land, not just evaporation to add.
+    /// Evaporation and convection depend on where the water is and how warm
+    /// it is, so this runs once water is placed and `set_water_temperatures`
+    /// has run.
+    pub fn simulate_moisture(&self, cells: &mut [Vec<TerrainCell>]) {
         self.calculate_rainfall(cells);
+        self.simulate_prevailing_winds(cells);
         self.apply_rain_shadows(cells);
+        
+        if self.rainfall_noise > 0.0 {
+            self.add_rainfall_variation(cells);
+        }
+        if self.rainfall_spread != 1.0 {
+            self.stretch_rainfall(cells);
+        }
+        
+        if self.projection == Projection::Equirectangular {
+            self.converge_poles(cells);
+        }
+    }
+    
+    /// Pull land temperature toward that of the nearest water, fading linearly to
+    /// nothing `MARITIME_RANGE` cells inland. Water is only known once sea level
+    /// is set, so this runs after water placement rather than in `calculate_temperature`.
+    pub fn moderate_coastal_temperatures(&self, cells: &mut [Vec<TerrainCell>]) {
+        if self.maritime_moderation <= 0.0 {
+            return;
+        }
+        
+        let reach = MARITIME_RANGE / self.world_scale;
+        let coast = nearest_coast(cells, self.topology);
+        let water_temperatures: Vec<Vec<Option<f32>>> = coast.iter().map(|row| {
+            row.iter().map(|coast| coast.across.map(|(x, y)| cells[y][x].temperature)).collect()
+        }).collect();
+        
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let distance = coast[y][x].distance as f32;
+                let Some(water_temperature) = water_temperatures[y][x] else { continue };
+                if !cell.is_water && distance < reach {
+                    let weight = self.maritime_moderation * (1.0 - distance / reach);
+                    cell.temperature += (water_temperature - cell.temperature) * weight;
+                }
+            }
+        }
+    }
+    
+    fn is_pole_row(&self, y: u32) -> bool {
+        self.projection == Projection::Equirectangular && (y == 0 || y == self.height - 1)
     }
     
-    fn calculate_temperature(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    /// In equirectangular mode every cell of the top (or bottom) row is the same
+    /// point on the globe, so give the whole row a single averaged climate.
+    fn converge_poles(&self, cells: &mut [Vec<TerrainCell>]) {
+        for y in [0, self.height as usize - 1] {
+            let row = &mut cells[y];
+            let count = row.len() as f32;
+            let temperature = row.iter().map(|cell| cell.temperature).sum::<f32>() / count;
+            let rainfall = row.iter().map(|cell| cell.rainfall).sum::<f32>() / count;
+            
+            for cell in row.iter_mut() {
+                cell.temperature = temperature;
+                cell.rainfall = rainfall;
+            }
+        }
+    }
+    
+    fn calculate_temperature(&self, cells: &mut [Vec<TerrainCell>]) {
         for y in 0..self.height {
             for x in 0..self.width {
-                let latitude_factor = (y as f32 / self.height as f32 - 0.5).abs();
                 let elevation = cells[y as usize][x as usize].elevation;
+                let elevation_cooling = elevation * LAPSE_RATE;
                 
-                let base_temp = 30.0 - latitude_factor * 40.0;
-                let elevation_cooling = elevation * 6.5;
+                cells[y as usize][x as usize].temperature =
+                    (self.base_temperature(y) - elevation_cooling + self.local_variation(x, y)).max(-20.0);
+            }
+        }
+    }
+    
+    /// Temperature at sea level for a row, from its distance to the thermal equator.
+    fn base_temperature(&self, y: u32) -> f32 {
+        // The map spans 180 degrees of latitude, and a positive offset moves the
+        // warmest band north (up)
+        let thermal_equator = 0.5 - self.axial_offset.clamp(-90.0, 90.0) / 180.0;
+        let latitude_factor = (self.row_fraction(y, false) - thermal_equator).abs();
+        30.0 - latitude_factor * 40.0 * self.temperature_spread
+    }
+    
+    /// Low-frequency wiggle so isotherms are not perfectly straight bands.
+    fn local_variation(&self, x: u32, y: u32) -> f32 {
+        if self.temperature_noise > 0.0 {
+            let scale = self.world_scale as f64 / 60.0;
+            self.temperature_perlin.get([x as f64 * scale, y as f64 * scale]) as f32 * self.temperature_noise
+        } else {
+            0.0
+        }
+    }
+    
+    /// Replace the land lapse-rate temperature of water cells with one from
+    /// latitude and depth below `sea_levels` (one per row): deeper water is
+    /// colder, down to `DEEP_WATER_TEMPERATURE` unless the surface is colder
+    /// still. The surface temperature is first mixed across neighbouring
+    /// water for `ocean_mixing` passes, standing in for the sea's thermal
+    /// inertia. Water is only known once sea level is set, so this runs after
+    /// `simulate_temperature` and before `simulate_moisture`.
+    pub fn set_water_temperatures(&self, cells: &mut [Vec<TerrainCell>], sea_levels: &[f32]) {
+        // Degrees C lost per unit of depth
+        const DEPTH_COOLING: f32 = 20.0;
+        const DEEP_WATER_TEMPERATURE: f32 = 4.0;
+        
+        let mut surface: Vec<Vec<f32>> = cells.iter().enumerate().map(|(y, row)| {
+            row.iter().enumerate().map(|(x, cell)| {
+                if cell.is_water { self.base_temperature(y as u32) + self.local_variation(x as u32, y as u32) } else { 0.0 }
+            }).collect()
+        }).collect();
+        self.mix_water_temperatures(cells, &mut surface);
+        
+        for (y, (row, &sea_level)) in cells.iter_mut().zip(sea_levels).enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !cell.is_water {
+                    continue;
+                }
+                let surface = surface[y][x];
+                let depth = (sea_level - cell.elevation).max(0.0);
+                let floor = surface.min(DEEP_WATER_TEMPERATURE);
+                cell.temperature = (surface - depth * DEPTH_COOLING).max(floor).max(-20.0);
+            }
+        }
+    }
+    
+    /// Average each water cell's surface temperature with its water
+    /// neighbours, repeatedly. Diffusion spreads with the square root of the
+    /// pass count, so passes shrink with the square of the world scale to
+    /// reach the same distance on a downscaled grid.
+    fn mix_water_temperatures(&self, cells: &[Vec<TerrainCell>], surface: &mut Vec<Vec<f32>>) {
+        let passes = (self.ocean_mixing as f32 / (self.world_scale * self.world_scale)).round() as usize;
+        let width = self.width as usize;
+        let height = self.height as usize;
+        
+        for _ in 0..passes {
+            let mixed = (0..height).map(|y| {
+                (0..width).map(|x| {
+                    if !cells[y][x].is_water {
+                        return surface[y][x];
+                    }
+                    let (mut total, mut count) = (surface[y][x], 1);
+                    for (nx, ny, _) in self.topology.neighbors(x, y, width, height) {
+                        if cells[ny][nx].is_water {
+                            total += surface[ny][nx];
+                            count += 1;
+                        }
+                    }
+                    total / count as f32
+                }).collect()
+            }).collect();
+            *surface = mixed;
+        }
+    }
+    
+    fn simulate_prevailing_winds(&self, cells: &mut [Vec<TerrainCell>]) {
+        for y in 0..self.height {
+            let (wind_x, wind_y) = self.prevailing_wind(y);
+            
+            for x in 0..self.width {
+                let moisture = self.calculate_atmospheric_moisture(x, y, cells);
                 
-                cells[y as usize][x as usize].temperature = (base_temp - elevation_cooling).max(-20.0);
+                if self.wind_model == WindModel::Hadley {
+                    self.transfer_along_wind(x, y, wind_x, wind_y, moisture * 0.1, cells);
+                } else if wind_x > 0.0 && x < self.width - 1 {
+                    self.transfer_moisture(x, y, x + 1, y, moisture * 0.1, cells);
+                } else if wind_x < 0.0 && x > 0 {
+                    self.transfer_moisture(x, y, x - 1, y, moisture * 0.1, cells);
+                }
             }
         }
     }
     
-    fn simulate_prevailing_winds(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    /// Carry moisture downwind, split between the zonal and meridional
+    /// neighbours in proportion to each wind component.
+    fn transfer_along_wind(&self, x: u32, y: u32, wind_x: f32, wind_y: f32,
+                           amount: f32, cells: &mut [Vec<TerrainCell>]) {
+        let to_x = x as i32 + wind_x.signum() as i32;
+        if wind_x != 0.0 && to_x >= 0 {
+            self.transfer_moisture(x, y, to_x as u32, y, amount * wind_x.abs(), cells);
+        }
+        
+        let to_y = y as i32 + wind_y.signum() as i32;
+        if wind_y != 0.0 && to_y >= 0 {
+            self.transfer_moisture(x, y, x, to_y as u32, amount * wind_y.abs(), cells);
+        }
+    }
+    
+    /// Prevailing wind vector for a row, as used by the moisture transport pass.
+    pub fn prevailing_wind(&self, y: u32) -> (f32, f32) {
+        // Winds converge at a pole and have no single direction there
+        if self.is_pole_row(y) {
+            return (0.0, 0.0);
+        }
+        
+        if self.wind_model == WindModel::Hadley {
+            return self.circulation_wind(y);
+        }
+        
+        let latitude = self.row_fraction(y, false);
+        
+        if latitude < 0.3 {
+            (1.0, 0.0)
+        } else if latitude < 0.6 {
+            (-1.0, 0.0)
+        } else {
+            (1.0, 0.0)
+        }
+    }
+    
+    /// Latitude of a row in radians, positive in the northern (top) half.
+    fn latitude(&self, y: u32) -> f32 {
+        (0.5 - self.row_fraction(y, true)) * std::f32::consts::PI
+    }
+    
+    /// How far row `y` lies from the north edge (0) to the south edge (1).
+    /// On an equirectangular map the first and last rows are the poles, so
+    /// rows span exactly 90N to 90S; flat maps measure from the row's top
+    /// edge, or its centre if `centred`.
+    fn row_fraction(&self, y: u32, centred: bool) -> f32 {
+        if self.projection == Projection::Equirectangular {
+            y as f32 / (self.height - 1).max(1) as f32
+        } else if centred {
+            (y as f32 + 0.5) / self.height as f32
+        } else {
+            y as f32 / self.height as f32
+        }
+    }
+    
+    /// Surface wind of the three-cell circulation. Each cell spans 30 degrees
+    /// of latitude; air sinks at 30 and rises at 0 and 60, so the surface flow
+    /// runs equatorward in the Hadley and polar cells and poleward in the
+    /// Ferrel cell. Coriolis deflection turns equatorward flow into easterlies
+    /// and poleward flow into westerlies. Winds are calm at the cell edges.
+    fn circulation_wind(&self, y: u32) -> (f32, f32) {
+        let latitude = self.latitude(y);
+        let strength = (6.0 * latitude.abs()).sin();
+        
+        // Positive strength means equatorward flow, which is +y (south) in the
+        // northern half of the map
+        (-strength, latitude.signum() * strength * 0.5)
+    }
+    
+    /// Rainfall from air rising where the meridional surface winds of
+    /// neighbouring rows converge, and the drying where they diverge. This
+    /// gives the wet equator, the dry subtropics near 30 degrees and the wet
+    /// polar front near 60 degrees.
+    fn convergence_rainfall(&self, y: u32) -> f32 {
+        const CONVERGENCE_RAINFALL: f32 = 1.0;
+        
+        if y == 0 || y == self.height - 1 {
+            return 0.0;
+        }
+        let north = self.circulation_wind(y - 1).1;
+        let south = self.circulation_wind(y + 1).1;
+        let row_angle = (self.row_fraction(1, true) - self.row_fraction(0, true)) * std::f32::consts::PI;
+        let convergence = (north - south) / (2.0 * row_angle);
+        
+        convergence * CONVERGENCE_RAINFALL
+    }
+    
+    /// Build the wind and surface-current vector grids for export. Currents are
+    /// wind-driven: a small fraction of the wind speed, deflected 45 degrees to
+    /// the right in the northern (top) half and to the left in the southern half.
+    pub fn flow_field(&self, cells: &[Vec<TerrainCell>]) -> FlowField {
+        let mut wind = vec![vec![(0.0, 0.0); self.width as usize]; self.height as usize];
+        let mut current = vec![vec![(0.0, 0.0); self.width as usize]; self.height as usize];
+        
         for y in 0..self.height {
-            let latitude = y as f32 / self.height as f32;
+            let (wx, wy) = self.prevailing_wind(y);
             
-            let wind_direction = if latitude < 0.3 {
-                1
-            } else if latitude < 0.6 {
-                -1
+            // With +y pointing south, a clockwise turn on the map is a positive angle
+            let deflection = if (y as f32) < self.height as f32 / 2.0 {
+                std::f32::consts::FRAC_PI_4
             } else {
-                1
+                -std::f32::consts::FRAC_PI_4
             };
+            let (sin, cos) = deflection.sin_cos();
             
             for x in 0..self.width {
-                let moisture = self.calculate_atmospheric_moisture(x, y, cells);
+                wind[y as usize][x as usize] = (wx, wy);
                 
-                if wind_direction > 0 && x < self.width - 1 {
-                    self.transfer_moisture(x, y, x + 1, y, moisture * 0.1, cells);
-                } else if wind_direction < 0 && x > 0 {
-                    self.transfer_moisture(x, y, x - 1, y, moisture * 0.1, cells);
+                if cells[y as usize][x as usize].is_water {
+                    current[y as usize][x as usize] = (
+                        (wx * cos - wy * sin) * 0.03,
+                        (wx * sin + wy * cos) * 0.03,
+                    );
                 }
             }
         }
+        
+        FlowField { wind, current }
     }
     
     fn calculate_atmospheric_moisture(&self, x: u32, y: u32, cells: &[Vec<TerrainCell>]) -> f32 {
@@ -66,17 +375,37 @@ impl ClimateSimulator {
         }
     }
     
-    fn transfer_moisture(&self, _from_x: u32, _from_y: u32, to_x: u32, to_y: u32, 
-                        amount: f32, cells: &mut Vec<Vec<TerrainCell>>) {
+    /// Move `amount` of moisture downwind. Land gives up what it passes on, so
+    /// the wind redistributes rainfall rather than adding to it; water is an
+    /// open evaporation source and keeps its own. Air leaving land carries
+    /// only `land_retention` of that on, raining the rest out where it is, so
+    /// with a short `moisture_range` rain stays near the coast it came from.
+    /// Nothing leaves over the map edge.
+    fn transfer_moisture(&self, from_x: u32, from_y: u32, to_x: u32, to_y: u32,
+                        amount: f32, cells: &mut [Vec<TerrainCell>]) {
         if to_x < self.width && to_y < self.height {
-            cells[to_y as usize][to_x as usize].rainfall += amount;
+            let source = &mut cells[from_y as usize][from_x as usize];
+            let moved = if source.is_water {
+                amount
+            } else {
+                amount.min(source.rainfall.max(0.0)) * self.land_retention
+            };
+            if !source.is_water {
+                source.rainfall -= moved;
+            }
+            cells[to_y as usize][to_x as usize].rainfall += moved;
         }
     }
     
-    fn calculate_rainfall(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    fn calculate_rainfall(&self, cells: &mut [Vec<TerrainCell>]) {
         for y in 0..self.height {
             for x in 0..self.width {
                 let convection_rainfall = self.calculate_convection_rainfall(x, y, cells);
+                let convergence_rainfall = if self.wind_model == WindModel::Hadley {
+                    self.convergence_rainfall(y)
+                } else {
+                    0.0
+                };
                 let cell = &mut cells[y as usize][x as usize];
                 
                 if !cell.is_water {
@@ -87,48 +416,66 @@ impl ClimateSimulator {
                         0.1
                     };
                     
-                    cell.rainfall += elevation_factor * temperature_factor * 5.0 + convection_rainfall;
-                    cell.rainfall = cell.rainfall.min(20.0);
+                    cell.rainfall += elevation_factor * temperature_factor * 5.0 + convection_rainfall + convergence_rainfall;
+                    cell.rainfall = cell.rainfall.clamp(0.0, 20.0);
                 }
             }
         }
     }
     
+    /// Perturb rainfall with patchy noise plus east-west stretched cloud bands,
+    /// so moisture forms mosaics instead of clean rings.
+    fn add_rainfall_variation(&self, cells: &mut [Vec<TerrainCell>]) {
+        let scale = self.world_scale as f64;
+        
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (wx, wy) = (x as f64 * scale, y as f64 * scale);
+                let patches = self.rainfall_perlin.get([wx / 40.0, wy / 40.0]) as f32;
+                let cloud_bands = self.rainfall_perlin.get([wx / 160.0, wy / 25.0, 1.0]) as f32;
+                let variation = (patches * 0.7 + cloud_bands * 0.3) * self.rainfall_noise;
+                
+                let cell = &mut cells[y as usize][x as usize];
+                cell.rainfall = (cell.rainfall + variation).clamp(0.0, 20.0);
+            }
+        }
+    }
+    
+    /// Scale each cell's rainfall away from (or toward) the map mean.
+    fn stretch_rainfall(&self, cells: &mut [Vec<TerrainCell>]) {
+        let count = (self.width * self.height) as f32;
+        let mean = cells.iter().flatten().map(|cell| cell.rainfall).sum::<f32>() / count;
+        
+        for cell in cells.iter_mut().flatten() {
+            cell.rainfall = (mean + (cell.rainfall - mean) * self.rainfall_spread).clamp(0.0, 20.0);
+        }
+    }
+    
+    /// Afternoon thunderstorms over hot ground near water, rising with every
+    /// degree above the trigger temperature.
     fn calculate_convection_rainfall(&self, x: u32, y: u32, cells: &[Vec<TerrainCell>]) -> f32 {
+        // Degrees above the trigger that give one unit of heat factor
+        const HEAT_RANGE: f32 = 10.0;
+        
         let cell = &cells[y as usize][x as usize];
         
-        if cell.temperature > 25.0 {
-            let heat_factor = (cell.temperature - 25.0) / 10.0;
-            let nearby_water = self.count_nearby_water(x, y, cells) as f32 / 8.0;
+        if cell.temperature > self.convection_threshold {
+            let heat_factor = (cell.temperature - self.convection_threshold) / HEAT_RANGE;
+            let nearby_water = self.count_nearby_water(x, y, cells) as f32 / self.topology.neighbor_count() as f32;
             
-            heat_factor * nearby_water * 3.0
+            heat_factor * nearby_water * self.convection_strength
         } else {
             0.0
         }
     }
     
     fn count_nearby_water(&self, x: u32, y: u32, cells: &[Vec<TerrainCell>]) -> usize {
-        let mut count = 0;
-        
-        for dy in -1i32..=1 {
-            for dx in -1i32..=1 {
-                if dx == 0 && dy == 0 { continue; }
-                
-                let nx = x as i32 + dx;
-                let ny = y as i32 + dy;
-                
-                if nx >= 0 && nx < self.width as i32 && ny >= 0 && ny < self.height as i32 {
-                    if cells[ny as usize][nx as usize].is_water {
-                        count += 1;
-                    }
-                }
-            }
-        }
-        
-        count
+        self.topology.neighbors(x as usize, y as usize, self.width as usize, self.height as usize)
+            .filter(|&(nx, ny, _)| cells[ny][nx].is_water)
+            .count()
     }
     
-    fn apply_rain_shadows(&self, cells: &mut Vec<Vec<TerrainCell>>) {
+    fn apply_rain_shadows(&self, cells: &mut [Vec<TerrainCell>]) {
         for y in 0..self.height {
             for x in 1..self.width {
                 let current_elevation = cells[y as usize][x as usize].elevation;
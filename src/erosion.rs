@@ -0,0 +1,104 @@
+use crate::{TerrainCell, BiomeType, Topology};
+
+// Land this cold and high holds permanent ice
+const GLACIER_TEMPERATURE: f32 = 0.0;
+const GLACIER_ELEVATION: f32 = 1.0;
+// Only the lowest stretch of a glacial valley is drowned, and only if it is steep
+const MAX_FJORD_LENGTH: usize = 12;
+const MIN_FJORD_SLOPE: f32 = 0.04;
+const FJORD_DEPTH: f32 = 0.1;
+
+pub struct GlacialEroder {
+    width: u32,
+    height: u32,
+    topology: Topology,
+}
+
+impl GlacialEroder {
+    pub fn new(width: u32, height: u32, topology: Topology) -> Self {
+        Self { width, height, topology }
+    }
+    
+    pub fn is_glaciated(cell: &TerrainCell) -> bool {
+        !cell.is_water && cell.temperature < GLACIER_TEMPERATURE && cell.elevation > GLACIER_ELEVATION
+    }
+    
+    /// Carve fjords: follow steepest descent from each glaciated cell and, where
+    /// the path reaches the sea down a steep valley, flood its lower reach into a
+    /// narrow inlet. Returns the number of cells turned into water.
+    pub fn carve_fjords(&self, cells: &mut [Vec<TerrainCell>]) -> usize {
+        let mut sources = Vec::new();
+        for (y, row) in cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if Self::is_glaciated(cell) {
+                    sources.push((x, y));
+                }
+            }
+        }
+        
+        // Warm worlds have no glaciers and so no fjords
+        let mut carved = 0;
+        for (x, y) in sources {
+            carved += self.carve_valley(x, y, cells);
+        }
+        
+        carved
+    }
+    
+    fn carve_valley(&self, start_x: usize, start_y: usize, cells: &mut [Vec<TerrainCell>]) -> usize {
+        let mut path = vec![(start_x, start_y)];
+        let (mut x, mut y) = (start_x, start_y);
+        
+        let (mouth_x, mouth_y) = loop {
+            let Some((next_x, next_y)) = self.steepest_descent(x, y, cells) else {
+                return 0;
+            };
+            
+            if cells[next_y][next_x].is_water {
+                break (next_x, next_y);
+            }
+            
+            path.push((next_x, next_y));
+            x = next_x;
+            y = next_y;
+        };
+        
+        let sea_elevation = cells[mouth_y][mouth_x].elevation;
+        let reach = &path[path.len().saturating_sub(MAX_FJORD_LENGTH)..];
+        let (top_x, top_y) = reach[0];
+        let drop = cells[top_y][top_x].elevation - sea_elevation;
+        
+        if drop / reach.len() as f32 <= MIN_FJORD_SLOPE {
+            return 0;
+        }
+        
+        let mut carved = 0;
+        for &(x, y) in reach {
+            let cell = &mut cells[y][x];
+            if !cell.is_water {
+                cell.is_water = true;
+                cell.biome = BiomeType::Ocean;
+                cell.elevation = (sea_elevation - FJORD_DEPTH).max(0.0);
+                carved += 1;
+            }
+        }
+        
+        carved
+    }
+    
+    fn steepest_descent(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> Option<(usize, usize)> {
+        let current_elevation = cells[y][x].elevation;
+        let mut lowest = current_elevation;
+        let mut best = None;
+        
+        for (nx, ny, _) in self.topology.neighbors(x, y, self.width as usize, self.height as usize) {
+            let neighbor_elevation = cells[ny][nx].elevation;
+            if neighbor_elevation < lowest {
+                lowest = neighbor_elevation;
+                best = Some((nx, ny));
+            }
+        }
+        
+        best
+    }
+}
\ No newline at end of file
@@ -0,0 +1,84 @@
+use crate::plate_tectonics::Elevation;
+use noise::permutationtable::{NoiseHasher, PermutationTable};
+
+/// Lanes evaluated together; the arithmetic on each batch vectorizes, only the
+/// permutation lookups stay scalar.
+const LANES: usize = 8;
+
+/// 2D Perlin noise evaluated a row at a time. Bit-identical to
+/// `noise::Perlin::new(seed)`, but the row's y lattice terms are worked out
+/// once per call instead of once per sample.
+pub struct RowPerlin {
+    table: [u8; 256],
+}
+
+impl RowPerlin {
+    pub fn new(seed: u32) -> Self {
+        let perm = PermutationTable::new(seed);
+        let mut table = [0u8; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            *entry = perm.hash(&[i as isize]) as u8;
+        }
+        Self { table }
+    }
+    
+    /// Add `weight * noise(xs[i] / divisor, y)` to `out[i]` for every element.
+    pub fn add_row(&self, out: &mut [Elevation], xs: &[f64], divisor: f64, y: f64, weight: Elevation) {
+        let floor_y = y.floor();
+        let row = floor_y as isize;
+        let dy0 = y - floor_y;
+        let dy1 = dy0 - 1.0;
+        let v = quintic(dy0);
+        let row0 = (row & 0xff) as usize;
+        let row1 = ((row + 1) & 0xff) as usize;
+        
+        for (chunk, xs) in out.chunks_mut(LANES).zip(xs.chunks(LANES)) {
+            let mut hashes = [[0u8; 4]; LANES];
+            let mut dx0 = [0.0f64; LANES];
+            for (lane, &x) in xs.iter().enumerate() {
+                let x = x / divisor;
+                let floor_x = x.floor();
+                let column = floor_x as isize;
+                dx0[lane] = x - floor_x;
+                let c0 = self.table[(column & 0xff) as usize] as usize;
+                let c1 = self.table[((column + 1) & 0xff) as usize] as usize;
+                hashes[lane] = [self.table[c0 ^ row0], self.table[c1 ^ row0], self.table[c0 ^ row1], self.table[c1 ^ row1]];
+            }
+            
+            let mut values = [0.0f64; LANES];
+            for lane in 0..LANES {
+                let [h00, h10, h01, h11] = hashes[lane];
+                let dx = dx0[lane];
+                let g00 = gradient(h00, dx, dy0);
+                let g10 = gradient(h10, dx - 1.0, dy0);
+                let g01 = gradient(h01, dx, dy1);
+                let g11 = gradient(h11, dx - 1.0, dy1);
+                
+                let u = quintic(dx);
+                let k1 = g10 - g00;
+                let k2 = g01 - g00;
+                let k3 = g00 + g11 - g10 - g01;
+                let value = (g00 + k1 * u + k2 * v + k3 * u * v) * std::f64::consts::SQRT_2;
+                values[lane] = value.clamp(-1.0, 1.0);
+            }
+            
+            for (out, value) in chunk.iter_mut().zip(values) {
+                *out += value as Elevation * weight;
+            }
+        }
+    }
+}
+
+/// Dot product with one of the four diagonal gradients, chosen without a branch.
+#[inline(always)]
+fn gradient(hash: u8, x: f64, y: f64) -> f64 {
+    let sx = if hash & 1 == 0 { x } else { -x };
+    let sy = if hash & 2 == 0 { y } else { -y };
+    sx + sy
+}
+
+#[inline(always)]
+fn quintic(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
\ No newline at end of file
@@ -0,0 +1,828 @@
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+pub mod terrain;
+pub mod plate_tectonics;
+pub mod climate;
+pub mod biomes;
+pub mod rivers;
+pub mod erosion;
+pub mod output;
+pub mod morphology;
+pub mod regions;
+pub mod seed;
+pub mod bench;
+pub mod noise_source;
+#[cfg(feature = "fast-noise")]
+pub mod fast_noise;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainCell {
+    pub elevation: f32,
+    pub temperature: f32,
+    pub rainfall: f32,
+    pub plate_id: usize,
+    pub is_water: bool,
+    pub biome: BiomeType,
+    pub has_river: bool,
+    #[serde(default)]
+    pub land_bridge: bool,
+    /// Steepest elevation drop to a neighbour per cell width.
+    #[serde(default)]
+    pub slope: f32,
+    /// Number of cells, including this one, whose D8 flow path passes through here.
+    #[serde(default)]
+    pub flow_accumulation: u32,
+    /// Water in an enclosed lake rather than the sea, which reaches the map edge.
+    #[serde(default)]
+    pub is_freshwater: bool,
+    /// Index into `TerrainData::water_bodies` for water cells.
+    #[serde(default)]
+    pub water_body_id: Option<usize>,
+    /// Strahler stream order of a river cell, 0 where there is no river.
+    #[serde(default)]
+    pub river_order: u8,
+    /// Steps to the nearest cell across the coastline: to water for land cells,
+    /// to land for water cells. `u32::MAX` when the map has no coastline.
+    #[serde(default)]
+    pub coast_distance: u32,
+    /// Biome the cell's climate comes nearest to tipping into, for blending;
+    /// `None` for water and cells far from any biome boundary.
+    #[serde(default)]
+    pub secondary_biome: Option<BiomeType>,
+    /// How far to blend toward `secondary_biome`: 0 for none, approaching 0.5
+    /// right at the boundary.
+    #[serde(default)]
+    pub biome_blend: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum BiomeType {
+    Ocean,
+    Desert,
+    Grassland,
+    Forest,
+    Tundra,
+    Mountain,
+    /// Not assigned by generation, which keeps the land biome under rivers and
+    /// flags them with `has_river`; kept so older JSON still loads.
+    River,
+    Beach,
+    Rainforest,
+    SeaIce,
+    Glacier,
+    Mangrove,
+}
+
+impl BiomeType {
+    /// Biomes that occupy water cells rather than land.
+    pub fn is_sea(self) -> bool {
+        matches!(self, BiomeType::Ocean | BiomeType::SeaIce)
+    }
+}
+
+/// How the grid maps onto the world. `Equirectangular` treats the top and
+/// bottom rows as the poles of a globe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    #[default]
+    Flat,
+    Equirectangular,
+}
+
+/// How prevailing winds are derived from latitude. `Bands` is the original
+/// three-band pattern; `Hadley` models the Hadley, Ferrel and polar cells, with
+/// easterly trades, mid-latitude westerlies and polar easterlies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WindModel {
+    #[default]
+    Bands,
+    Hadley,
+}
+
+/// Where water collects. `Elevation` floods the lowest ground anywhere;
+/// `Polar` and `Equatorial` lower sea level toward the equator or the poles
+/// respectively, so water pools at high or low latitudes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WaterMode {
+    #[default]
+    Elevation,
+    Polar,
+    Equatorial,
+}
+
+/// Mirroring applied to the map for balanced multiplayer layouts. `Horizontal`
+/// mirrors left to right, `Vertical` top to bottom, and `Rotational` turns the
+/// map half a turn about its centre.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Symmetry {
+    #[default]
+    None,
+    Horizontal,
+    Vertical,
+    Rotational,
+}
+
+impl Symmetry {
+    /// Cell that (x, y) mirrors to on a `width` by `height` grid.
+    pub fn mirror(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Symmetry::None => (x, y),
+            Symmetry::Horizontal => (width - 1 - x, y),
+            Symmetry::Vertical => (x, height - 1 - y),
+            Symmetry::Rotational => (width - 1 - x, height - 1 - y),
+        }
+    }
+}
+
+/// Colour scheme of the main PNG. `OldMap` is a posterized sepia parchment
+/// look with inked coastlines and flat water.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum MapStyle {
+    #[default]
+    Realistic,
+    OldMap,
+}
+
+/// Neighbours a river may step to on the square grid. `Four` keeps to edge
+/// neighbours so channels never cross diagonally; hex grids ignore it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum RiverConnectivity {
+    #[value(name = "4")]
+    #[serde(rename = "4")]
+    Four,
+    #[default]
+    #[value(name = "8")]
+    #[serde(rename = "8")]
+    Eight,
+}
+
+/// Cell adjacency used by the simulation. `Hex` uses "odd-r" offset rows: odd
+/// rows sit half a cell to the right, giving every cell six neighbours.
+/// Mask operations in `morphology` stay 8-neighbour under either topology.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    #[default]
+    Square,
+    Hex,
+}
+
+const SQUARE_NEIGHBORS: [(i32, i32, f32); 8] = [
+    (-1, -1, std::f32::consts::SQRT_2), (0, -1, 1.0), (1, -1, std::f32::consts::SQRT_2),
+    (-1, 0, 1.0), (1, 0, 1.0),
+    (-1, 1, std::f32::consts::SQRT_2), (0, 1, 1.0), (1, 1, std::f32::consts::SQRT_2),
+];
+const HEX_EVEN_ROW_NEIGHBORS: [(i32, i32, f32); 6] = [
+    (-1, -1, 1.0), (0, -1, 1.0),
+    (-1, 0, 1.0), (1, 0, 1.0),
+    (-1, 1, 1.0), (0, 1, 1.0),
+];
+const HEX_ODD_ROW_NEIGHBORS: [(i32, i32, f32); 6] = [
+    (0, -1, 1.0), (1, -1, 1.0),
+    (-1, 0, 1.0), (1, 0, 1.0),
+    (0, 1, 1.0), (1, 1, 1.0),
+];
+
+impl Topology {
+    /// Maximum number of neighbours a cell can have.
+    pub fn neighbor_count(self) -> usize {
+        match self {
+            Topology::Square => SQUARE_NEIGHBORS.len(),
+            Topology::Hex => HEX_EVEN_ROW_NEIGHBORS.len(),
+        }
+    }
+    
+    /// In-bounds neighbours of cell (x, y) as `(nx, ny, distance)`, where distance
+    /// is between cell centres in cell widths. Square neighbours come in row-major
+    /// order, matching a `for dy { for dx { .. } }` scan.
+    pub fn neighbors(self, x: usize, y: usize, width: usize, height: usize) -> impl Iterator<Item = (usize, usize, f32)> {
+        let offsets: &'static [(i32, i32, f32)] = match self {
+            Topology::Square => &SQUARE_NEIGHBORS,
+            Topology::Hex if y % 2 == 0 => &HEX_EVEN_ROW_NEIGHBORS,
+            Topology::Hex => &HEX_ODD_ROW_NEIGHBORS,
+        };
+        
+        offsets.iter().filter_map(move |&(dx, dy, distance)| {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            
+            if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
+                Some((nx as usize, ny as usize, distance))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TectonicPlate {
+    pub id: usize,
+    pub center: (f32, f32),
+    pub velocity: (f32, f32),
+    pub age: f32,
+    pub plate_type: PlateType,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PlateType {
+    Oceanic,
+    Continental,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TerrainData {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<Vec<TerrainCell>>,
+    pub plates: Vec<TectonicPlate>,
+    pub generation_params: GenerationParams,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow_field: Option<FlowField>,
+    /// Per-row elevation above which land is permanently snow-covered.
+    #[serde(default)]
+    pub snow_line: Vec<f32>,
+    /// Per-row elevation above which trees no longer grow.
+    #[serde(default)]
+    pub tree_line: Vec<f32>,
+    /// Connected bodies of water, indexed by `TerrainCell::water_body_id`.
+    #[serde(default)]
+    pub water_bodies: Vec<WaterBody>,
+    /// Per-row elevation at or below which cells were flooded; it varies by
+    /// row under the polar and equatorial water modes.
+    #[serde(default)]
+    pub sea_levels: Vec<f32>,
+    #[serde(skip)]
+    elevation_cache: ElevationCache,
+}
+
+/// A connected body of water. Bodies reaching the map edge are part of the
+/// open ocean; enclosed ones are inland seas or, if small, freshwater lakes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterBody {
+    pub id: usize,
+    pub size: usize,
+    pub is_connected_to_edge: bool,
+    #[serde(default)]
+    pub is_lake: bool,
+}
+
+/// One bit per cell, packed row-major, for boolean layers of large maps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellMask {
+    width: usize,
+    height: usize,
+    bits: Vec<u64>,
+}
+
+impl CellMask {
+    fn from_fn(width: usize, height: usize, f: impl Fn(usize, usize) -> bool) -> Self {
+        let mut bits = vec![0u64; (width * height).div_ceil(64)];
+        for y in 0..height {
+            for x in 0..width {
+                if f(x, y) {
+                    let index = y * width + x;
+                    bits[index / 64] |= 1 << (index % 64);
+                }
+            }
+        }
+        Self { width, height, bits }
+    }
+    
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    
+    /// Whether cell (x, y) is set; false outside the map.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let index = y * self.width + x;
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+    
+    /// Number of set cells.
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|word| word.count_ones() as usize).sum()
+    }
+    
+    /// Packed words; bit `i % 64` of word `i / 64` is cell `i = y * width + x`.
+    pub fn as_words(&self) -> &[u64] {
+        &self.bits
+    }
+}
+
+/// Minimum, maximum and mean of a set of values.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RangeStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+impl RangeStats {
+    fn of(values: impl Iterator<Item = f32>) -> Option<Self> {
+        let (mut min, mut max, mut sum, mut count) = (f32::INFINITY, f32::NEG_INFINITY, 0.0, 0);
+        for value in values {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+            count += 1;
+        }
+        (count > 0).then(|| Self { min, max, mean: sum / count as f32 })
+    }
+}
+
+/// A cell picked out as a world fact, with its elevation.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct NotableCell {
+    pub x: usize,
+    pub y: usize,
+    pub elevation: f32,
+}
+
+/// The largest enclosed water body, located by its deepest cell.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct NotableLake {
+    pub id: usize,
+    pub size: usize,
+    pub deepest: NotableCell,
+}
+
+/// Summary figures for a finished world, as printed by `--verbose`.
+/// Rainfall is in simulation units.
+#[derive(Debug, Clone, Serialize)]
+pub struct TerrainStats {
+    pub land_cells: usize,
+    pub water_cells: usize,
+    pub river_cells: usize,
+    pub plates: usize,
+    pub water_bodies: usize,
+    pub inland_water_bodies: usize,
+    pub sea_level: Option<RangeStats>,
+    pub snow_line: Option<RangeStats>,
+    pub land_rainfall: Option<RangeStats>,
+    pub highest_point: Option<NotableCell>,
+    /// Lowest cell anywhere, normally the deepest ocean floor.
+    pub lowest_point: Option<NotableCell>,
+    pub largest_lake: Option<NotableLake>,
+    pub biomes: std::collections::BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Default)]
+struct ElevationCache {
+    all: OnceLock<Vec<f32>>,
+    land: OnceLock<Vec<f32>>,
+}
+
+impl TerrainData {
+    /// Snow line for row `y`. Terrain loaded from older JSON without computed
+    /// lines falls back to the old fixed snow elevation.
+    pub fn snow_line_at(&self, y: usize) -> f32 {
+        self.snow_line.get(y).copied().unwrap_or(2.5)
+    }
+    
+    /// Sea level for row `y`. Terrain loaded from older JSON without per-row
+    /// levels falls back to the elevation at the requested water percentile.
+    pub fn sea_level_at(&self, y: usize) -> f32 {
+        self.sea_levels.get(y).copied()
+            .or_else(|| self.elevation_at_percentile(self.generation_params.water_percentage, false))
+            .unwrap_or(0.0)
+    }
+    
+    /// Every cell as `(x, y, cell)` in row-major order: `x` is the column
+    /// (0..width) and `y` the row (0..height), so the cell is `cells[y][x]`.
+    pub fn iter_cells(&self) -> impl Iterator<Item = (usize, usize, &TerrainCell)> {
+        self.cells.iter().enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, cell)| (x, y, cell)))
+    }
+    
+    /// Mutable counterpart of `iter_cells`, with the same `(x, y)` convention.
+    /// Clears the cached elevations behind `elevation_at_percentile`.
+    pub fn iter_cells_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut TerrainCell)> {
+        self.elevation_cache = ElevationCache::default();
+        self.cells.iter_mut().enumerate()
+            .flat_map(|(y, row)| row.iter_mut().enumerate().map(move |(x, cell)| (x, y, cell)))
+    }
+    
+    /// The cell nearest normalized coordinates `(u, v)`, where (0, 0) is the
+    /// top-left corner and (1, 1) the bottom-right. Inputs outside [0, 1],
+    /// including NaN, are clamped to the map edge.
+    pub fn sample(&self, u: f32, v: f32) -> &TerrainCell {
+        let to_index = |t: f32, size: u32| {
+            let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+            ((t * size as f32) as usize).min(size as usize - 1)
+        };
+        &self.cells[to_index(v, self.height)][to_index(u, self.width)]
+    }
+    
+    /// Land cells as `mask[y][x]`, the complement of `is_water`.
+    pub fn land_mask(&self) -> Vec<Vec<bool>> {
+        self.cells.iter().map(|row| row.iter().map(|cell| !cell.is_water).collect()).collect()
+    }
+    
+    /// Water cells as `mask[y][x]`, straight from `is_water`.
+    pub fn water_mask(&self) -> Vec<Vec<bool>> {
+        self.cells.iter().map(|row| row.iter().map(|cell| cell.is_water).collect()).collect()
+    }
+    
+    /// Bit-packed `land_mask`, an eighth the size of a `bool` grid.
+    pub fn land_bitmask(&self) -> CellMask {
+        CellMask::from_fn(self.width as usize, self.height as usize, |x, y| !self.cells[y][x].is_water)
+    }
+    
+    /// Bit-packed `water_mask`.
+    pub fn water_bitmask(&self) -> CellMask {
+        CellMask::from_fn(self.width as usize, self.height as usize, |x, y| self.cells[y][x].is_water)
+    }
+    
+    pub fn stats(&self) -> TerrainStats {
+        let cells = || self.cells.iter().flatten();
+        let water_cells = cells().filter(|cell| cell.is_water).count();
+        
+        let mut biomes = std::collections::BTreeMap::new();
+        for cell in cells() {
+            *biomes.entry(format!("{:?}", cell.biome)).or_insert(0) += 1;
+        }
+        
+        TerrainStats {
+            land_cells: cells().count() - water_cells,
+            water_cells,
+            river_cells: cells().filter(|cell| cell.has_river).count(),
+            plates: self.plates.len(),
+            water_bodies: self.water_bodies.len(),
+            inland_water_bodies: self.water_bodies.iter().filter(|body| !body.is_connected_to_edge).count(),
+            sea_level: RangeStats::of((0..self.height as usize).map(|y| self.sea_level_at(y))),
+            snow_line: RangeStats::of(self.snow_line.iter().copied()),
+            land_rainfall: RangeStats::of(cells().filter(|cell| !cell.is_water).map(|cell| cell.rainfall)),
+            highest_point: self.notable_cell(|_| true, |a, b| a > b),
+            lowest_point: self.notable_cell(|_| true, |a, b| a < b),
+            largest_lake: self.largest_lake(),
+            biomes,
+        }
+    }
+    
+    /// First cell in row-major order among those passing `filter` whose
+    /// elevation no other beats under `better`.
+    fn notable_cell(&self, filter: impl Fn(&TerrainCell) -> bool, better: impl Fn(f32, f32) -> bool) -> Option<NotableCell> {
+        let mut best: Option<NotableCell> = None;
+        for (x, y, cell) in self.iter_cells().filter(|&(_, _, cell)| filter(cell)) {
+            if best.is_none_or(|best| better(cell.elevation, best.elevation)) {
+                best = Some(NotableCell { x, y, elevation: cell.elevation });
+            }
+        }
+        best
+    }
+    
+    fn largest_lake(&self) -> Option<NotableLake> {
+        let lake = self.water_bodies.iter()
+            .filter(|body| body.is_lake)
+            .max_by_key(|body| (body.size, std::cmp::Reverse(body.id)))?;
+        let deepest = self.notable_cell(|cell| cell.water_body_id == Some(lake.id), |a, b| a < b)?;
+        Some(NotableLake { id: lake.id, size: lake.size, deepest })
+    }
+    
+    /// Percentage of cells that are water.
+    pub fn water_percentage(&self) -> f32 {
+        let total = self.iter_cells().count();
+        let water = self.iter_cells().filter(|(_, _, cell)| cell.is_water).count();
+        water as f32 * 100.0 / total.max(1) as f32
+    }
+    
+    /// Whether water coverage missed what `generation_params` asked for: all
+    /// land or all water when some of each was wanted, or more than 25 points
+    /// off. Sea level is set at the requested percentile, so this catches
+    /// elevation ties, as on flat ground that floods all at once, and coast
+    /// clean-up that moves much land across the shoreline. A requested 0% or
+    /// 100% is met, not degenerate.
+    pub fn is_degenerate(&self) -> bool {
+        const TOLERANCE: f32 = 25.0;
+        
+        let requested = self.generation_params.water_percentage.clamp(0.0, 100.0);
+        let water = self.water_percentage();
+        let all_or_nothing = (water <= 0.0 && requested > 0.0) || (water >= 100.0 && requested < 100.0);
+        all_or_nothing || (water - requested).abs() > TOLERANCE
+    }
+    
+    /// Number of distinct biomes present on land cells.
+    pub fn land_biome_count(&self) -> usize {
+        let mut seen = Vec::new();
+        for cell in self.cells.iter().flatten() {
+            if !cell.biome.is_sea() && !seen.contains(&cell.biome) {
+                seen.push(cell.biome);
+            }
+        }
+        seen.len()
+    }
+    
+    /// Elevation at `percentile` (0-100) of all cells, or of land cells only.
+    /// The sorted elevations are computed on first use and cached until
+    /// `iter_cells_mut` is next called; changes made through `cells` directly
+    /// are not seen. Returns `None` if no cells qualify.
+    pub fn elevation_at_percentile(&self, percentile: f32, land_only: bool) -> Option<f32> {
+        let sorted = if land_only {
+            self.elevation_cache.land.get_or_init(|| {
+                sorted_elevations(self.cells.iter().flatten().filter(|cell| !cell.is_water))
+            })
+        } else {
+            self.elevation_cache.all.get_or_init(|| sorted_elevations(self.cells.iter().flatten()))
+        };
+        
+        percentile_of_sorted(sorted, percentile)
+    }
+}
+
+/// Generate a `width` by `height` world and render it as PNG bytes with the
+/// default render options, without touching the filesystem. This is the entry
+/// point for wasm32 builds with default features off.
+pub fn generate_and_render_png_bytes(width: u32, height: u32, params: GenerationParams) -> Vec<u8> {
+    let terrain = terrain::TerrainGenerator::new(width, height, params).generate();
+    output::encode_png(&terrain, &output::RenderOptions::default()).expect("PNG encoding into memory failed")
+}
+
+/// Steepest elevation change from cell (x, y) to any neighbour, per cell width.
+pub fn max_slope(cells: &[Vec<TerrainCell>], topology: Topology, x: usize, y: usize) -> f32 {
+    let current_elevation = cells[y][x].elevation;
+    let mut max_slope: f32 = 0.0;
+    
+    for (nx, ny, distance) in topology.neighbors(x, y, cells[0].len(), cells.len()) {
+        let elevation_diff = (current_elevation - cells[ny][nx].elevation).abs();
+        max_slope = max_slope.max(elevation_diff / distance);
+    }
+    
+    max_slope
+}
+
+/// Breadth-first distance transform from the coastline: each cell's step count
+/// to the nearest cell with the opposite `is_water`, `u32::MAX` if there is none.
+pub fn coast_distances(cells: &[Vec<TerrainCell>], topology: Topology) -> Vec<Vec<u32>> {
+    nearest_coast(cells, topology).into_iter()
+        .map(|row| row.into_iter().map(|coast| coast.distance).collect())
+        .collect()
+}
+
+/// A cell's distance to the coast and the cell across it that the distance is
+/// measured to: for land, the nearest water, and for water, the nearest land.
+#[derive(Debug, Clone, Copy)]
+pub struct CoastDistance {
+    pub distance: u32,
+    pub across: Option<(usize, usize)>,
+}
+
+/// `coast_distances` with the cell across the coast each is measured to.
+pub fn nearest_coast(cells: &[Vec<TerrainCell>], topology: Topology) -> Vec<Vec<CoastDistance>> {
+    let height = cells.len();
+    let width = cells[0].len();
+    let mut coast = vec![vec![CoastDistance { distance: u32::MAX, across: None }; width]; height];
+    let mut queue = std::collections::VecDeque::new();
+    
+    for y in 0..height {
+        for x in 0..width {
+            let is_water = cells[y][x].is_water;
+            let across = topology.neighbors(x, y, width, height).find(|&(nx, ny, _)| cells[ny][nx].is_water != is_water);
+            if let Some((nx, ny, _)) = across {
+                coast[y][x] = CoastDistance { distance: 1, across: Some((nx, ny)) };
+                queue.push_back((x, y));
+            }
+        }
+    }
+    
+    // Land and water spread separately, so a cell only takes a distance from its own side
+    while let Some((x, y)) = queue.pop_front() {
+        let next = CoastDistance { distance: coast[y][x].distance + 1, ..coast[y][x] };
+        for (nx, ny, _) in topology.neighbors(x, y, width, height) {
+            if cells[ny][nx].is_water == cells[y][x].is_water && coast[ny][nx].distance == u32::MAX {
+                coast[ny][nx] = next;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    
+    coast
+}
+
+/// `f` applied to each row index in `0..height`, in order. Rows run on the
+/// rayon pool with the `parallel` feature, so `f` must not depend on the
+/// order rows are visited.
+pub(crate) fn map_rows<T: Send>(height: usize, f: impl Fn(usize) -> T + Sync + Send) -> Vec<T> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        (0..height).into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        (0..height).map(f).collect()
+    }
+}
+
+/// Elevations of the given cells in ascending order.
+pub(crate) fn sorted_elevations<'a>(cells: impl Iterator<Item = &'a TerrainCell>) -> Vec<f32> {
+    let mut elevations: Vec<f32> = cells.map(|cell| cell.elevation).collect();
+    elevations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    elevations
+}
+
+/// Nearest-rank percentile (0-100) of an ascending slice; out-of-range
+/// percentiles clamp to the lowest or highest value.
+pub(crate) fn percentile_of_sorted(sorted: &[f32], percentile: f32) -> Option<f32> {
+    let index = (sorted.len() as f32 * percentile / 100.0) as usize;
+    sorted.get(index.min(sorted.len().checked_sub(1)?)).copied()
+}
+
+/// Per-cell flow vectors from the climate pass, indexed `[y][x]` like `cells`.
+/// Vectors are in cell units per step with +x east and +y south.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowField {
+    /// Prevailing wind, which depends only on latitude.
+    pub wind: Vec<Vec<(f32, f32)>>,
+    /// Surface ocean currents, zero on land. These are driven by the wind
+    /// alone, not simulated: a small fraction of the wind turned 45 degrees,
+    /// so they ignore coastlines and do not form gyres.
+    pub current: Vec<Vec<(f32, f32)>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    pub water_percentage: f32,
+    pub seed: u64,
+    /// Non-numeric seed text the original `seed` was hashed from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed_name: Option<String>,
+    pub plate_count: usize,
+    pub flow_vectors: bool,
+    /// Land necks narrower than about this many cells that join two wider
+    /// landmasses are flooded, or marked if `flag_land_bridges`; 0 disables.
+    pub min_land_width: usize,
+    pub flag_land_bridges: bool,
+    pub connect_islands: usize,
+    /// Land components with fewer cells than this are flooded; 0 keeps all.
+    pub min_island_size: usize,
+    pub sea_ice_temperature: f32,
+    pub preview: bool,
+    pub plate_speed: f32,
+    pub projection: Projection,
+    pub fjords: bool,
+    pub temperature_noise: f32,
+    pub rainfall_noise: f32,
+    pub topology: Topology,
+    pub temperature_spread: f32,
+    pub rainfall_spread: f32,
+    pub min_biomes: usize,
+    pub wind_model: WindModel,
+    pub water_mode: WaterMode,
+    pub symmetry: Symmetry,
+    /// Stop after tectonics and water, leaving climate, biomes and rivers blank.
+    pub terrain_only: bool,
+    pub min_river_length: usize,
+    pub river_connectivity: RiverConnectivity,
+    /// Trace at most this many rivers, keeping the most prominent sources (0 for no cap).
+    pub max_rivers: usize,
+    /// Fill land depressions before tracing so every river reaches water.
+    pub require_outlet: bool,
+    /// Rainfall-equivalent that permanent snow and ice add when choosing
+    /// river sources (0 disables).
+    pub snowmelt: f32,
+    pub coast_smoothing: usize,
+    pub polar_bias: f32,
+    /// Majority-filter passes over land biomes; more passes give larger regions.
+    pub biome_smoothing_passes: usize,
+    /// How strongly biomes avoid ground higher or steeper than they favour (0 disables).
+    pub biome_elevation_weight: f32,
+    /// Elevation above which land is Mountain.
+    pub mountain_elevation: f32,
+    /// If above 0, the Mountain cutoff is instead the land elevation at this
+    /// percentile (0-100), so it follows the map's own elevation spread.
+    pub mountain_percentile: f32,
+    /// Skip the beach pass, leaving shores their climate biome. Coastal forest
+    /// and mangrove still grow.
+    pub no_beaches: bool,
+    /// Widest beach in cells, reached on the flattest coasts and narrowing to
+    /// none on cliffs (0 keeps the one-cell ring of low shore).
+    pub beach_width: u32,
+    pub axial_offset: f32,
+    /// Temperature in degrees C above which convection adds rainfall.
+    pub convection_threshold: f32,
+    /// Convection rainfall per heat factor at full water exposure.
+    pub convection_strength: f32,
+    /// How strongly nearby water pulls coastal land temperature toward its own,
+    /// 0 to 1; 0 disables maritime moderation.
+    pub maritime_moderation: f32,
+    /// Passes of mixing that smooth sea-surface temperature across
+    /// neighbouring water (0 disables).
+    pub ocean_mixing: usize,
+    /// Distance in world units over which wind crossing land carries a factor
+    /// of e less moisture; the rest rains out on the way, so shorter ranges
+    /// dry continental interiors (0 disables).
+    pub moisture_range: f32,
+    /// Times to re-run with a re-derived seed while the world's water
+    /// coverage is far from `water_percentage` (0 keeps the first world).
+    pub degenerate_retries: usize,
+    /// Share of plates placed as spread-out continental plates. Sea level is an
+    /// elevation percentile set by `water_percentage`, so this changes where land
+    /// rises and how mountainous it is, not how much of the map is land.
+    pub continental_fraction: f32,
+    /// Radius of the ring continental plates start on, as a fraction of the
+    /// shorter map side; 0 gathers them into one supercontinent.
+    pub continent_spread: f32,
+    /// Random variation of each continental plate's ring radius, in cells.
+    pub continent_jitter: f32,
+    /// Power applied to normalized base elevation; above 1 gives broad plains
+    /// and sharp highlands, below 1 broad plateaus. 1 is linear.
+    pub elevation_exponent: f32,
+    /// Domain warp on base elevation: noise samples are pushed up to this many
+    /// world units off their cell, giving swirlier coasts and ranges (0 disables).
+    pub warp_strength: f32,
+    /// How far, in world units, noise pushes plate boundaries off their
+    /// straight Voronoi edges (0 keeps them straight).
+    pub boundary_roughness: f32,
+    /// Strength of mid-ocean ridges and abyssal noise on the sea floor (0 disables).
+    pub ocean_floor: f32,
+    /// Chance that each remaining plate turns continental anyway.
+    pub oceanic_flip_chance: f64,
+    /// Cells over which boundary uplift and rifting fade out (1 keeps them on the boundary).
+    pub boundary_width: usize,
+    /// Authored plates used before any random ones; `None` keeps all plates random.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_plates: Option<Vec<plate_tectonics::PlateSeed>>,
+    /// With pinned plates, add random plates up to the usual random count.
+    pub top_up_plates: bool,
+    /// Custom climate-to-biome table; `None` uses the built-in rules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub biome_rules: Option<Vec<biomes::BiomeRule>>,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        Self {
+            water_percentage: 30.0,
+            seed: 42,
+            seed_name: None,
+            plate_count: 0,
+            flow_vectors: false,
+            min_land_width: 0,
+            flag_land_bridges: false,
+            connect_islands: 0,
+            min_island_size: 0,
+            sea_ice_temperature: -1.8,
+            preview: false,
+            plate_speed: 1.0,
+            projection: Projection::Flat,
+            fjords: false,
+            temperature_noise: 0.0,
+            rainfall_noise: 0.0,
+            topology: Topology::Square,
+            temperature_spread: 1.0,
+            rainfall_spread: 1.0,
+            min_biomes: 0,
+            wind_model: WindModel::Bands,
+            water_mode: WaterMode::Elevation,
+            symmetry: Symmetry::None,
+            terrain_only: false,
+            min_river_length: 4,
+            river_connectivity: RiverConnectivity::Eight,
+            max_rivers: 0,
+            require_outlet: false,
+            snowmelt: 0.0,
+            coast_smoothing: 0,
+            polar_bias: 0.0,
+            biome_smoothing_passes: 1,
+            biome_elevation_weight: 0.0,
+            mountain_elevation: 2.0,
+            mountain_percentile: 0.0,
+            no_beaches: false,
+            beach_width: 0,
+            biome_rules: None,
+            pinned_plates: None,
+            top_up_plates: false,
+            axial_offset: 0.0,
+            convection_threshold: 25.0,
+            convection_strength: 3.0,
+            maritime_moderation: 0.0,
+            ocean_mixing: 0,
+            moisture_range: 0.0,
+            degenerate_retries: 0,
+            continental_fraction: 0.4,
+            oceanic_flip_chance: 0.2,
+            continent_spread: 0.3,
+            continent_jitter: 50.0,
+            elevation_exponent: 1.0,
+            warp_strength: 0.0,
+            boundary_roughness: 0.0,
+            ocean_floor: 0.0,
+            boundary_width: 1,
+        }
+    }
+}
\ No newline at end of file
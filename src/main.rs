@@ -1,19 +1,30 @@
-use clap::Parser;
-use serde::{Deserialize, Serialize};
+use clap::{Parser, Subcommand};
+use terrain_generator::{bench, biomes, output, plate_tectonics, regions, seed, GenerationParams, MapStyle, Projection, RiverConnectivity, Symmetry, TerrainStats, Topology, WaterMode, WindModel};
+use terrain_generator::terrain::TerrainGenerator;
 
-mod terrain;
-mod plate_tectonics;
-mod climate;
-mod biomes;
-mod rivers;
-mod output;
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
 
-use terrain::TerrainGenerator;
+/// Status output, shown unless `--quiet` is given.
+macro_rules! info {
+    ($verbosity:expr, $($arg:tt)*) => {
+        if $verbosity >= Verbosity::Normal {
+            println!($($arg)*);
+        }
+    };
+}
 
 #[derive(Parser)]
 #[command(name = "terrain-generator")]
 #[command(about = "Generate realistic terrain for fictional worlds")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    
     #[arg(short, long, default_value = "2048")]
     width: u32,
     
@@ -26,90 +37,729 @@ struct Args {
     #[arg(short, long, default_value = "terrain")]
     output: String,
     
+    /// Generate this many maps from consecutive seeds, written as <output>_0, <output>_1, ...
+    #[arg(long, default_value = "1")]
+    count: usize,
+    
+    /// World seed: a number, or any text, which is hashed to a number
     #[arg(long, default_value = "42")]
-    seed: u64,
+    seed: String,
     
     #[arg(long, default_value = "false")]
     json: bool,
+    
+    /// Store per-cell wind and ocean-current vectors in the JSON output.
+    /// Currents are derived from the wind, not simulated
+    #[arg(long)]
+    flow_vectors: bool,
+    
+    /// Detect land necks narrower than about this many cells (0 disables)
+    #[arg(long, default_value = "0")]
+    min_land_width: usize,
+    
+    /// Only mark detected land bridges instead of flooding them
+    #[arg(long)]
+    flag_land_bridges: bool,
+    
+    /// Raise shallow straits up to this many cells wide that separate landmasses (0 disables)
+    #[arg(long, default_value = "0")]
+    connect_islands: usize,
+    
+    /// Flood islands with fewer than this many cells (0 keeps all)
+    #[arg(long, default_value = "0")]
+    min_island_size: usize,
+    
+    /// Water colder than this (degrees C) freezes into sea ice
+    #[arg(long, default_value = "-1.8")]
+    sea_ice_temperature: f32,
+    
+    /// Generate a fast low-resolution preview of the same world
+    #[arg(long)]
+    preview: bool,
+    
+    /// Compare the generated terrain against a previously exported JSON file
+    #[arg(long, value_name = "OTHER_JSON")]
+    diff: Option<String>,
+    
+    /// Multiplier on random plate velocities; higher values build taller ranges
+    #[arg(long, default_value = "1.0")]
+    plate_speed: f32,
+    
+    /// Map projection; equirectangular makes the top and bottom rows poles
+    #[arg(long, value_enum, default_value = "flat")]
+    projection: Projection,
+    
+    /// With an equirectangular projection, set the width to twice the height so the map spans 360 by 180 degrees
+    #[arg(long)]
+    fix_aspect: bool,
+    
+    /// Carve fjords where glaciated highlands meet the sea down steep valleys
+    #[arg(long)]
+    fjords: bool,
+    
+    /// Amplitude in degrees C of local temperature noise (0 disables)
+    #[arg(long, default_value = "0.0")]
+    temp_noise: f32,
+    
+    /// Amplitude in rainfall units of patchy rainfall noise (0 disables)
+    #[arg(long, default_value = "0.0")]
+    rain_noise: f32,
+    
+    /// Upscale the PNG by this integer factor with nearest-neighbour (JSON is unaffected)
+    #[arg(long, default_value = "1")]
+    scale: u32,
+    
+    /// Also export elevation as a georeferenced GeoTIFF (<output>.tif)
+    #[arg(long)]
+    geotiff: bool,
+    
+    /// Also export elevation as raw little-endian f32 with a width/height header (<output>.f32)
+    #[arg(long)]
+    raw_elevation: bool,
+    
+    /// Level water cells to their water body's surface in the raw and GeoTIFF elevation exports
+    #[arg(long)]
+    flat_water: bool,
+    
+    /// Also export an elevation cross-section from x0,y0 to x1,y1 as CSV (<output>_profile.csv)
+    #[arg(long, value_name = "X0,Y0,X1,Y1")]
+    profile: Option<output::ProfileLine>,
+    
+    /// Also export contiguous same-biome land regions and their neighbours as JSON (<output>_regions.json)
+    #[arg(long)]
+    regions: bool,
+    
+    /// Also export plate boundaries as GeoJSON lines (<output>_plates.geojson)
+    #[arg(long)]
+    plate_boundaries: bool,
+    
+    /// GeoTIFF and GeoJSON extent as min_lon,min_lat,max_lon,max_lat in degrees
+    #[arg(long, default_value = "-180,-90,180,90", allow_hyphen_values = true)]
+    bbox: output::GeoBounds,
+    
+    /// Cell adjacency for the simulation: 8-neighbour square or 6-neighbour hex
+    #[arg(long, value_enum, default_value = "square")]
+    topology: Topology,
+    
+    /// Retry with a wider climate spread until at least this many land biomes appear
+    #[arg(long, default_value = "0")]
+    min_biomes: usize,
+    
+    /// Also export a grayscale slope map (<output>_slope.png)
+    #[arg(long)]
+    slope_map: bool,
+    
+    /// Also export a grayscale distance-to-coast map (<output>_coast_distance.png)
+    #[arg(long)]
+    coast_distance_map: bool,
+    
+    /// Also export each land cell's D8 flow direction, colour-coded, with drainage pits in white (<output>_flow.png)
+    #[arg(long)]
+    flow_field: bool,
+    
+    /// Prevailing wind model: simple latitude bands or three-cell circulation
+    #[arg(long, value_enum, default_value = "bands")]
+    wind_model: WindModel,
+    
+    /// Where water pools: lowest ground anywhere, or biased toward the poles or the equator
+    #[arg(long, value_enum, default_value = "elevation")]
+    water_mode: WaterMode,
+    
+    /// Mirror the map for fairness: left-right, top-bottom or a half turn
+    #[arg(long, value_enum, default_value = "none")]
+    symmetry: Symmetry,
+    
+    /// Drop rivers with fewer than this many cells
+    #[arg(long, default_value = "4")]
+    min_river_length: usize,
+    
+    /// Directions a river may flow on the square grid: 4 for edge neighbours only, giving clean non-crossing networks, or 8
+    #[arg(long, value_enum, default_value = "8")]
+    river_connectivity: RiverConnectivity,
+    
+    /// Trace at most this many rivers, keeping the most prominent sources (0 for no cap)
+    #[arg(long, default_value = "0")]
+    max_rivers: usize,
+    
+    /// Fill land depressions so every river drains to water instead of stopping in a pit
+    #[arg(long)]
+    require_outlet: bool,
+    
+    /// Rainfall-equivalent that permanent snow and ice add to river sources, so cold dry ranges feed rivers (0 disables)
+    #[arg(long, default_value = "0")]
+    snowmelt: f32,
+    
+    /// Coastline smoothing radius in cells; clears islands and lakes narrower than about twice this (0 disables)
+    #[arg(long, default_value = "0")]
+    coast_smoothing: usize,
+    
+    /// Fraction of latitude next to each pole pushed toward tundra and ice cap (0 disables)
+    #[arg(long, default_value = "0.0")]
+    polar_bias: f32,
+    
+    /// Biome smoothing passes; more passes merge speckle into larger regions
+    #[arg(long, default_value = "1")]
+    biome_smoothing: usize,
+    
+    /// How strongly forest, rainforest and desert avoid high or steep ground (0 disables)
+    #[arg(long, default_value = "0.0")]
+    biome_elevation_weight: f32,
+    
+    /// Elevation above which land becomes the Mountain biome
+    #[arg(long, default_value = "2.0")]
+    mountain_elevation: f32,
+    
+    /// Instead make the top land cells above this elevation percentile Mountain (0 disables)
+    #[arg(long, default_value = "0.0")]
+    mountain_percentile: f32,
+    
+    /// Leave coasts their climate biome instead of turning low shores into beach; coastal forest and mangrove still grow
+    #[arg(long)]
+    no_beaches: bool,
+    
+    /// Widest beach in cells, on the flattest coasts; steeper shores get narrower beaches and cliffs none (0 keeps the low-shore ring)
+    #[arg(long, default_value = "0")]
+    beach_width: u32,
+    
+    /// JSON file of ordered climate-to-biome rules replacing the built-in table
+    #[arg(long, value_name = "FILE")]
+    biome_rules: Option<String>,
+    
+    /// JSON file of plates ({"x", "y", "plate_type", optional "velocity"}) placed before random ones
+    #[arg(long, value_name = "FILE")]
+    plates: Option<String>,
+    
+    /// With --plates, add random plates until the usual random count is reached
+    #[arg(long, requires = "plates")]
+    top_up_plates: bool,
+    
+    /// Degrees of latitude to move the warmest band north (negative for south)
+    #[arg(long, default_value = "0.0", allow_hyphen_values = true)]
+    axial_offset: f32,
+    
+    /// Temperature in degrees C above which convection storms add rainfall
+    #[arg(long, default_value = "25.0", allow_hyphen_values = true)]
+    convection_threshold: f32,
+    
+    /// Strength of convection rainfall over hot ground near water
+    #[arg(long, default_value = "3.0")]
+    convection_strength: f32,
+    
+    /// Pull coastal temperatures toward the nearby water's, 0 (off) to 1
+    #[arg(long, default_value = "0.0")]
+    maritime_moderation: f32,
+    
+    /// Passes smoothing sea-surface temperature across neighbouring water, for the sea's thermal inertia (0 disables)
+    #[arg(long, default_value = "0")]
+    ocean_mixing: usize,
+    
+    /// World units over which wind-borne moisture decays by a factor of e over land (0 disables)
+    #[arg(long, default_value = "0")]
+    moisture_range: f32,
+    
+    /// Re-seed and retry up to this many times if the water coverage is far off target
+    #[arg(long, default_value = "0")]
+    degenerate_retries: usize,
+    
+    /// Treat a degenerate (nearly all land or all water) world as an error
+    #[arg(long)]
+    strict: bool,
+    
+    /// Share of plates that are continental; water coverage stays set by --water-percentage
+    #[arg(long, default_value = "0.4")]
+    continental_fraction: f32,
+    
+    /// Ring radius for continental plates as a fraction of the shorter side (0 for one supercontinent)
+    #[arg(long, default_value = "0.3")]
+    continent_spread: f32,
+    
+    /// Random variation in cells of each continental plate's distance from the centre
+    #[arg(long, default_value = "50.0")]
+    continent_jitter: f32,
+    
+    /// Power curve on base elevation: above 1 flattens lowlands and sharpens highlands
+    #[arg(long, default_value = "1.0")]
+    elevation_exponent: f32,
+    
+    /// Domain warp on base elevation, in cells of offset; bends coasts and ranges into more organic shapes (0 disables)
+    #[arg(long, default_value = "0")]
+    warp_strength: f32,
+    
+    /// Wiggle plate boundaries by up to this many cells so fault lines and ranges look jagged (0 keeps straight Voronoi edges)
+    #[arg(long, default_value = "0")]
+    boundary_roughness: f32,
+    
+    /// Strength of sea floor detail: mid-ocean ridges and abyssal noise (0 disables)
+    #[arg(long, default_value = "0.0")]
+    ocean_floor: f32,
+    
+    /// Chance that each non-continental plate becomes continental anyway
+    #[arg(long, default_value = "0.2", value_parser = parse_chance)]
+    oceanic_flip_chance: f64,
+    
+    /// Distance in cells over which mountains and rifts fade away from plate boundaries
+    #[arg(long, default_value = "1")]
+    boundary_width: usize,
+    
+    /// Report rainfall in mm/year, at this many mm/year per internal rainfall unit
+    #[arg(long, value_name = "MM_PER_UNIT")]
+    rainfall_scale: Option<f32>,
+    
+    /// Only run tectonics and water assignment; skip climate, biomes and rivers
+    #[arg(long)]
+    terrain_only: bool,
+    
+    /// Rotate the hue of ocean, lake and river colours by this many degrees
+    #[arg(long, default_value = "0.0", allow_hyphen_values = true)]
+    water_tint: f32,
+    
+    /// Write the PNG as RGBA with water cells fully transparent
+    #[arg(long)]
+    transparent_water: bool,
+    
+    /// Colour scheme of the PNG: realistic biomes or a sepia parchment map
+    #[arg(long, value_enum, default_value = "realistic")]
+    style: MapStyle,
+    
+    /// Opacity of small rivers over the terrain, 0 to 1; larger rivers are drawn more solid
+    #[arg(long, default_value = "1.0")]
+    river_opacity: f32,
+    
+    /// Draw a faint labelled grid at this spacing: degrees on an equirectangular map, cells otherwise (0 for none)
+    #[arg(long, default_value = "0")]
+    graticule: f32,
+    
+    /// Soften biome seams in the PNG by blending similar neighbouring colours and upscaling smoothly (classification is unchanged)
+    #[arg(long)]
+    color_smoothing: bool,
+    
+    /// Write a PNG after each generation stage (<output>_01_plates.png etc.)
+    #[arg(long)]
+    debug_stages: bool,
+    
+    /// Write an animated GIF of this many frames of plate drift (<output>_plates.gif)
+    #[cfg(feature = "animate")]
+    #[arg(long, value_name = "FRAMES")]
+    animate_plates: Option<usize>,
+    
+    /// Write generation parameters, stage timings and statistics to this JSON file
+    #[arg(long, value_name = "FILE")]
+    report: Option<String>,
+    
+    /// Flag large single-biome or flat areas, which usually mean a parameter problem
+    #[arg(long)]
+    analyze: bool,
+    
+    /// Smallest area, as a percentage of the map, that --analyze reports
+    #[arg(long, default_value = "10.0", requires = "analyze")]
+    analyze_area: f32,
+    
+    /// Generate the world twice and check both serialize identically, then exit
+    #[arg(long)]
+    selfcheck: bool,
+    
+    /// Worker threads for the parallel passes (0 uses every core, 1 runs serially)
+    #[cfg(feature = "parallel")]
+    #[arg(long, default_value = "0")]
+    threads: usize,
+    
+    /// Print nothing but errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    
+    /// Print per-stage timings, cell counts and chosen thresholds
+    #[arg(short, long)]
+    verbose: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TerrainCell {
-    pub elevation: f32,
-    pub temperature: f32,
-    pub rainfall: f32,
-    pub plate_id: usize,
-    pub is_water: bool,
-    pub biome: BiomeType,
-    pub has_river: bool,
+#[derive(Subcommand)]
+enum Command {
+    /// Time generation over a fixed set of sizes and seeds, one JSON object per line
+    Bench,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub enum BiomeType {
-    Ocean,
-    Desert,
-    Grassland,
-    Forest,
-    Tundra,
-    Mountain,
-    River,
-    Beach,
-    Rainforest,
+fn main() {
+    let mut args = Args::parse();
+    
+    #[cfg(feature = "parallel")]
+    if args.threads > 0 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(args.threads)
+            .build_global()
+            .expect("Failed to configure thread pool");
+    }
+    
+    if let Some(Command::Bench) = args.command {
+        bench::run();
+        return;
+    }
+    
+    let verbosity = if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    
+    // A globe texture covers 360 degrees of longitude to 180 of latitude
+    if args.projection == Projection::Equirectangular && args.width != 2 * args.height {
+        if args.fix_aspect {
+            args.width = 2 * args.height;
+        } else if verbosity != Verbosity::Quiet {
+            eprintln!("Warning: equirectangular map is {}x{}, not 2:1, so it will not wrap onto a globe undistorted (see --fix-aspect)",
+                args.width, args.height);
+        }
+    }
+    
+    let mut generator = TerrainGenerator::new(
+        args.width,
+        args.height,
+        GenerationParams {
+            water_percentage: args.water_percentage,
+            seed: seed::parse_seed(&args.seed),
+            seed_name: args.seed.parse::<u64>().is_err().then(|| args.seed.clone()),
+            flow_vectors: args.flow_vectors,
+            min_land_width: args.min_land_width,
+            flag_land_bridges: args.flag_land_bridges,
+            connect_islands: args.connect_islands,
+            min_island_size: args.min_island_size,
+            sea_ice_temperature: args.sea_ice_temperature,
+            preview: args.preview,
+            plate_speed: args.plate_speed,
+            projection: args.projection,
+            fjords: args.fjords,
+            temperature_noise: args.temp_noise,
+            rainfall_noise: args.rain_noise,
+            topology: args.topology,
+            min_biomes: args.min_biomes,
+            wind_model: args.wind_model,
+            water_mode: args.water_mode,
+            symmetry: args.symmetry,
+            terrain_only: args.terrain_only,
+            min_river_length: args.min_river_length,
+            river_connectivity: args.river_connectivity,
+            max_rivers: args.max_rivers,
+            require_outlet: args.require_outlet,
+            snowmelt: args.snowmelt,
+            coast_smoothing: args.coast_smoothing,
+            polar_bias: args.polar_bias,
+            biome_smoothing_passes: args.biome_smoothing,
+            biome_elevation_weight: args.biome_elevation_weight,
+            mountain_elevation: args.mountain_elevation,
+            mountain_percentile: args.mountain_percentile,
+            no_beaches: args.no_beaches,
+            beach_width: args.beach_width,
+            axial_offset: args.axial_offset,
+            convection_threshold: args.convection_threshold,
+            convection_strength: args.convection_strength,
+            maritime_moderation: args.maritime_moderation,
+            ocean_mixing: args.ocean_mixing,
+            moisture_range: args.moisture_range,
+            degenerate_retries: args.degenerate_retries,
+            continental_fraction: args.continental_fraction,
+            oceanic_flip_chance: args.oceanic_flip_chance,
+            continent_spread: args.continent_spread,
+            continent_jitter: args.continent_jitter,
+            elevation_exponent: args.elevation_exponent,
+            warp_strength: args.warp_strength,
+            boundary_roughness: args.boundary_roughness,
+            ocean_floor: args.ocean_floor,
+            boundary_width: args.boundary_width,
+            pinned_plates: args.plates.as_deref().map(|path| {
+                plate_tectonics::load_plate_seeds(path).expect("Failed to load plates")
+            }),
+            top_up_plates: args.top_up_plates,
+            biome_rules: args.biome_rules.as_deref().map(|path| {
+                biomes::load_biome_rules(path).expect("Failed to load biome rules")
+            }),
+            ..GenerationParams::default()
+        },
+    );
+    
+    if args.selfcheck {
+        info!(verbosity, "Self-check: generating seed {} twice...", args.seed);
+        let (first, second) = (selfcheck_hash(&mut generator), selfcheck_hash(&mut generator));
+        if first != second {
+            eprintln!("Self-check FAILED: {:016x} then {:016x}", first, second);
+            std::process::exit(1);
+        }
+        info!(verbosity, "Self-check passed: {:016x}", first);
+        return;
+    }
+    
+    generator.capture_stages(args.debug_stages);
+    
+    if args.count <= 1 {
+        generate_map(&args, &mut generator, &args.output, args.report.as_deref(), false, verbosity);
+    } else {
+        let base_seed = generator.params_mut().seed;
+        for i in 0..args.count {
+            let params = generator.params_mut();
+            params.seed = base_seed.wrapping_add(i as u64);
+            if i > 0 {
+                params.seed_name = None;
+            }
+            info!(verbosity, "Map {} of {}:", i + 1, args.count);
+            let report_path = args.report.as_deref().map(|path| numbered_path(path, i));
+            generate_map(&args, &mut generator, &format!("{}_{}", args.output, i), report_path.as_deref(), true, verbosity);
+        }
+    }
+    
+    info!(verbosity, "Terrain generation complete!");
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TectonicPlate {
-    pub id: usize,
-    pub center: (f32, f32),
-    pub velocity: (f32, f32),
-    pub age: f32,
-    pub plate_type: PlateType,
+/// Generate one world and write every requested export under `output`.
+/// A `batch` run also prints a one-line summary of the map.
+fn generate_map(args: &Args, generator: &mut TerrainGenerator, output: &str, report_path: Option<&str>,
+                batch: bool, verbosity: Verbosity) {
+    info!(verbosity, "Generating terrain...");
+    let terrain_data = generator.generate();
+    let reseeds = generator.degenerate_retries();
+    if reseeds.degenerate {
+        if args.strict {
+            eprintln!("Error: degenerate world with {:.1}% water", reseeds.water_percentage);
+            std::process::exit(1);
+        }
+        if verbosity != Verbosity::Quiet {
+            eprintln!("Warning: degenerate world with {:.1}% water after {} retries (wanted {:.1}%)",
+                reseeds.water_percentage, reseeds.retries, args.water_percentage);
+        }
+    }
+    let biome_retries = generator.biome_retries();
+    if biome_retries.land_biomes < args.min_biomes && verbosity != Verbosity::Quiet {
+        eprintln!("Warning: only {} distinct land biomes after {} retries (wanted {})",
+            biome_retries.land_biomes, biome_retries.retries, args.min_biomes);
+    }
+    if args.max_rivers > 0 {
+        let stats = generator.river_stats();
+        info!(verbosity, "River sources: kept {} of {} found", stats.sources_kept, stats.sources_found);
+    }
+    let stats = terrain_data.stats();
+    if batch {
+        info!(verbosity, "  seed {}: {} land, {} water cells, {} land biomes", terrain_data.generation_params.seed,
+            stats.land_cells, stats.water_cells, terrain_data.land_biome_count());
+    }
+    if verbosity == Verbosity::Verbose {
+        print_details(generator, &stats, args.rainfall_scale);
+    }
+    let uniform_regions = args.analyze.then(|| regions::uniform_regions(&terrain_data, args.analyze_area));
+    if let Some(uniform_regions) = &uniform_regions {
+        print_uniform_regions(uniform_regions);
+    }
+    if let Some(report_path) = report_path {
+        let mut report = serde_json::json!({
+            "generation_params": terrain_data.generation_params,
+            "timings": generator.timings(),
+            "stats": stats,
+            "rivers": generator.river_stats(),
+        });
+        if let Some(uniform_regions) = &uniform_regions {
+            report["uniform_regions"] = serde_json::json!(uniform_regions);
+        }
+        std::fs::write(report_path, serde_json::to_string_pretty(&report).expect("Failed to serialize report"))
+            .expect("Failed to write report");
+    }
+    
+    let output_name = if args.preview {
+        info!(verbosity, "Preview mode: {}x{} grid for a {}x{} world",
+            terrain_data.width, terrain_data.height, args.width, args.height);
+        format!("{}_preview", output)
+    } else {
+        output.to_string()
+    };
+    
+    let render_options = output::RenderOptions {
+        scale: args.scale,
+        water_tint: args.water_tint,
+        transparent_water: args.transparent_water,
+        style: args.style,
+        river_opacity: args.river_opacity,
+        graticule: args.graticule,
+        color_smoothing: args.color_smoothing,
+    };
+    
+    info!(verbosity, "Exporting PNG image...");
+    output::export_png(&terrain_data, &format!("{}.png", output_name), &render_options)
+        .expect("Failed to export PNG");
+    
+    if args.debug_stages {
+        info!(verbosity, "Exporting stage snapshots...");
+        for (i, snapshot) in generator.stage_snapshots().iter().enumerate() {
+            let filename = format!("{}_{:02}_{}.png", output_name, i + 1, snapshot.name);
+            output::export_stage_png(&terrain_data, snapshot, &filename, &render_options)
+                .expect("Failed to export stage snapshot");
+        }
+    }
+    
+    #[cfg(feature = "animate")]
+    if let Some(frames) = args.animate_plates {
+        info!(verbosity, "Animating plate drift...");
+        let (width, height) = (terrain_data.width, terrain_data.height);
+        output::export_plate_animation(&generator.animate_plates(frames), width, height,
+            args.water_percentage, &format!("{}_plates.gif", output_name), args.scale)
+            .expect("Failed to export plate animation");
+    }
+    
+    if args.json {
+        info!(verbosity, "Exporting JSON data...");
+        output::export_json(&terrain_data, &format!("{}.json", output_name))
+            .expect("Failed to export JSON");
+    }
+    
+    if args.slope_map {
+        info!(verbosity, "Exporting slope map...");
+        output::export_slope_map(&terrain_data, &format!("{}_slope.png", output_name))
+            .expect("Failed to export slope map");
+    }
+    
+    if args.coast_distance_map {
+        info!(verbosity, "Exporting coast distance map...");
+        output::export_coast_distance_map(&terrain_data, &format!("{}_coast_distance.png", output_name))
+            .expect("Failed to export coast distance map");
+    }
+    
+    if args.flow_field {
+        info!(verbosity, "Exporting flow direction map...");
+        output::export_flow_direction_map(&terrain_data, &format!("{}_flow.png", output_name))
+            .expect("Failed to export flow direction map");
+    }
+    
+    if args.raw_elevation {
+        info!(verbosity, "Exporting raw elevation...");
+        output::export_raw_elevation(&terrain_data, &format!("{}.f32", output_name), args.flat_water)
+            .expect("Failed to export raw elevation");
+    }
+    
+    if args.geotiff {
+        info!(verbosity, "Exporting GeoTIFF...");
+        output::export_geotiff(&terrain_data, &format!("{}.tif", output_name), args.bbox, args.flat_water)
+            .expect("Failed to export GeoTIFF");
+    }
+    
+    if let Some(line) = args.profile {
+        info!(verbosity, "Exporting elevation profile...");
+        output::export_profile_csv(&terrain_data, line, &format!("{}_profile.csv", output_name))
+            .expect("Failed to export elevation profile");
+    }
+    
+    if args.regions {
+        info!(verbosity, "Exporting region graph...");
+        output::export_region_graph(&terrain_data, &format!("{}_regions.json", output_name))
+            .expect("Failed to export region graph");
+    }
+    
+    if args.plate_boundaries {
+        info!(verbosity, "Exporting plate boundaries...");
+        output::export_plate_boundaries_geojson(&terrain_data, &format!("{}_plates.geojson", output_name), args.bbox)
+            .expect("Failed to export plate boundaries");
+    }
+    
+    if let Some(other_path) = &args.diff {
+        info!(verbosity, "Comparing against {}...", other_path);
+        let other = output::import_json(other_path)
+            .expect("Failed to load comparison JSON");
+        let summary = output::export_diff_png(&terrain_data, &other, &format!("{}_diff.png", output_name))
+            .expect("Failed to export diff image");
+        
+        info!(verbosity, "Changed cells: {} of {}", summary.changed_cells, summary.total_cells);
+        info!(verbosity, "  water/land: {}", summary.water_changed);
+        info!(verbosity, "  biome:      {}", summary.biome_changed);
+        info!(verbosity, "  river:      {}", summary.river_changed);
+    }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum PlateType {
-    Oceanic,
-    Continental,
+/// A probability for clap; out-of-range values are clamped later, but NaN and
+/// infinities have no sensible clamp.
+fn parse_chance(value: &str) -> Result<f64, String> {
+    let chance: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if !chance.is_finite() {
+        return Err(format!("{} is not a finite number", value));
+    }
+    Ok(chance)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TerrainData {
-    pub width: u32,
-    pub height: u32,
-    pub cells: Vec<Vec<TerrainCell>>,
-    pub plates: Vec<TectonicPlate>,
-    pub generation_params: GenerationParams,
+/// `path` with `_<index>` added before its extension.
+fn numbered_path(path: &str, index: usize) -> String {
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().map_or_else(Default::default, |stem| stem.to_string_lossy());
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, index),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GenerationParams {
-    pub water_percentage: f32,
-    pub seed: u64,
-    pub plate_count: usize,
+/// Hash of one generated world's serialized form.
+fn selfcheck_hash(generator: &mut TerrainGenerator) -> u64 {
+    use std::hash::{Hash, Hasher};
+    
+    let bytes = serde_json::to_vec(&generator.generate()).expect("Failed to serialize terrain");
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
 }
 
-fn main() {
-    let args = Args::parse();
+fn print_uniform_regions(uniform_regions: &[regions::UniformRegion]) {
+    if uniform_regions.is_empty() {
+        println!("Analysis: no large uniform regions");
+        return;
+    }
     
-    let mut generator = TerrainGenerator::new(
-        args.width,
-        args.height,
-        args.water_percentage,
-        args.seed,
-    );
+    println!("Analysis: {} large uniform region(s)", uniform_regions.len());
+    for region in uniform_regions {
+        let kind = match region.kind {
+            regions::Uniformity::Biome(biome) => format!("{:?} biome", biome),
+            regions::Uniformity::FlatElevation => "flat elevation".to_string(),
+        };
+        println!("  {} at ({:.0}, {:.0}): {} cells, {:.1}% of map",
+            kind, region.centroid.0, region.centroid.1, region.area, region.percent);
+    }
+}
+
+fn print_details(generator: &TerrainGenerator, stats: &TerrainStats, rainfall_scale: Option<f32>) {
+    for timing in generator.timings() {
+        println!("  {:<10} {:.3}s", timing.stage, timing.seconds);
+    }
     
-    println!("Generating terrain...");
-    let terrain_data = generator.generate();
+    println!("  cells: {} land, {} water, {} river", stats.land_cells, stats.water_cells, stats.river_cells);
+    let river_stats = generator.river_stats();
+    println!("  rivers: {} ({} stuck in pits)", river_stats.rivers, river_stats.stuck);
+    if let Some((x, y)) = river_stats.longest_river_source {
+        println!("  longest river: {} cells from ({}, {})", river_stats.longest_river, x, y);
+    }
+    println!("  plates: {}", stats.plates);
+    println!("  water bodies: {} ({} inland)", stats.water_bodies, stats.inland_water_bodies);
     
-    println!("Exporting PNG image...");
-    output::export_png(&terrain_data, &format!("{}.png", args.output))
-        .expect("Failed to export PNG");
+    if let Some(sea_level) = stats.sea_level {
+        println!("  sea level: {:.3} to {:.3}", sea_level.min, sea_level.max);
+    }
+    if let Some(snow_line) = stats.snow_line {
+        println!("  snow line: {:.3} to {:.3}", snow_line.min, snow_line.max);
+    }
+    if let Some(peak) = stats.highest_point {
+        println!("  highest point: {:.3} at ({}, {})", peak.elevation, peak.x, peak.y);
+    }
+    if let Some(low) = stats.lowest_point {
+        println!("  lowest point: {:.3} at ({}, {})", low.elevation, low.x, low.y);
+    }
+    if let Some(lake) = stats.largest_lake {
+        println!("  largest lake: {} cells, deepest {:.3} at ({}, {})", lake.size, lake.deepest.elevation, lake.deepest.x, lake.deepest.y);
+    }
     
-    if args.json {
-        println!("Exporting JSON data...");
-        output::export_json(&terrain_data, &format!("{}.json", args.output))
-            .expect("Failed to export JSON");
+    // Simulation rainfall units are arbitrary; scale them for display only
+    if let Some(rainfall) = stats.land_rainfall {
+        let (scale, unit) = match rainfall_scale {
+            Some(scale) => (scale, " mm/year"),
+            None => (1.0, " units"),
+        };
+        println!("  land rainfall: {:.1} to {:.1}{}, mean {:.1}",
+            rainfall.min * scale, rainfall.max * scale, unit, rainfall.mean * scale);
     }
     
-    println!("Terrain generation complete!");
+    let mut biomes: Vec<_> = stats.biomes.iter().collect();
+    biomes.sort_by_key(|&(_, count)| std::cmp::Reverse(*count));
+    for (biome, count) in biomes {
+        println!("  {:<10} {}", biome, count);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,236 @@
+// Binary morphology and connected-component labelling on masks indexed [y][x].
+// These always use the square 8-neighbourhood, even when the simulation runs
+// on hex topology; there it treats two extra diagonals per cell as adjacent,
+// so water bodies and land regions can join across a corner the hex grid
+// itself would not connect.
+
+pub fn erode(mask: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    apply_neighborhood(mask, true)
+}
+
+pub fn dilate(mask: &[Vec<bool>]) -> Vec<Vec<bool>> {
+    apply_neighborhood(mask, false)
+}
+
+/// Erode then dilate `radius` times: removes features narrower than about
+/// `2 * radius + 1` cells while keeping the shape of wider ones.
+pub fn open(mask: &[Vec<bool>], radius: usize) -> Vec<Vec<bool>> {
+    let mut result = mask.to_vec();
+    for _ in 0..radius {
+        result = erode(&result);
+    }
+    for _ in 0..radius {
+        result = dilate(&result);
+    }
+    result
+}
+
+/// Dilate then erode `radius` times: fills gaps narrower than about
+/// `2 * radius + 1` cells.
+pub fn close(mask: &[Vec<bool>], radius: usize) -> Vec<Vec<bool>> {
+    let mut result = mask.to_vec();
+    for _ in 0..radius {
+        result = dilate(&result);
+    }
+    for _ in 0..radius {
+        result = erode(&result);
+    }
+    result
+}
+
+fn apply_neighborhood(mask: &[Vec<bool>], erode: bool) -> Vec<Vec<bool>> {
+    let height = mask.len();
+    let width = mask[0].len();
+    let mut result = mask.to_vec();
+    
+    for y in 0..height {
+        for x in 0..width {
+            // Erosion keeps a cell only if every neighbour is set; dilation sets it
+            // if any neighbour is. Out-of-bounds neighbours are ignored.
+            let mut value = mask[y][x];
+            
+            for dy in -1i32..=1 {
+                for dx in -1i32..=1 {
+                    if dx == 0 && dy == 0 { continue; }
+                    
+                    let nx = x as i32 + dx;
+                    let ny = y as i32 + dy;
+                    
+                    if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
+                        let neighbor = mask[ny as usize][nx as usize];
+                        if erode {
+                            value &= neighbor;
+                        } else {
+                            value |= neighbor;
+                        }
+                    }
+                }
+            }
+            
+            result[y][x] = value;
+        }
+    }
+    
+    result
+}
+
+/// Label 8-connected components of set cells. Returns the label grid (`None` for
+/// unset cells) and the number of components; labels are assigned in scan order.
+pub fn label_components(mask: &[Vec<bool>]) -> (Vec<Vec<Option<usize>>>, usize) {
+    let height = mask.len();
+    let width = mask[0].len();
+    let mut labels = vec![vec![None; width]; height];
+    let mut count = 0;
+    let mut stack = Vec::new();
+    
+    for start_y in 0..height {
+        for start_x in 0..width {
+            if !mask[start_y][start_x] || labels[start_y][start_x].is_some() {
+                continue;
+            }
+            
+            labels[start_y][start_x] = Some(count);
+            stack.push((start_x, start_y));
+            
+            while let Some((x, y)) = stack.pop() {
+                for dy in -1i32..=1 {
+                    for dx in -1i32..=1 {
+                        if dx == 0 && dy == 0 { continue; }
+                        
+                        let nx = x as i32 + dx;
+                        let ny = y as i32 + dy;
+                        
+                        if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
+                            let (nx, ny) = (nx as usize, ny as usize);
+                            if mask[ny][nx] && labels[ny][nx].is_none() {
+                                labels[ny][nx] = Some(count);
+                                stack.push((nx, ny));
+                            }
+                        }
+                    }
+                }
+            }
+            
+            count += 1;
+        }
+    }
+    
+    (labels, count)
+}
+
+/// For each component of `region`, the set of distinct `core` labels it touches
+/// (8-adjacent or overlapping). Used to find necks and straits that join two
+/// otherwise separate bodies.
+pub fn touching_labels(
+    region: &[Vec<Option<usize>>],
+    region_count: usize,
+    core: &[Vec<Option<usize>>],
+) -> Vec<Vec<usize>> {
+    let height = region.len();
+    let width = region[0].len();
+    let mut touching: Vec<Vec<usize>> = vec![Vec::new(); region_count];
+    
+    for (y, row) in region.iter().enumerate() {
+        for (x, &label) in row.iter().enumerate() {
+            let Some(id) = label else { continue };
+            
+            for dy in -1i32..=1 {
+                for dx in -1i32..=1 {
+                    let nx = x as i32 + dx;
+                    let ny = y as i32 + dy;
+                    
+                    if nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32 {
+                        if let Some(core_id) = core[ny as usize][nx as usize] {
+                            if !touching[id].contains(&core_id) {
+                                touching[id].push(core_id);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    touching
+}
+
+/// Cells of `region` components lying on a near-shortest 8-connected path,
+/// within the component, between two distinct `core` components it touches:
+/// those whose distances to the two cores sum to at most `slack` more than
+/// the shortest such sum. These form the neck that joins the cores, while
+/// the rest of the component is fringe hanging off a single core.
+pub fn necks(
+    region: &[Vec<Option<usize>>],
+    region_count: usize,
+    core: &[Vec<Option<usize>>],
+    slack: u32,
+) -> Vec<Vec<bool>> {
+    let height = region.len();
+    let width = region[0].len();
+    let touching = touching_labels(region, region_count, core);
+    let mut members: Vec<Vec<(usize, usize)>> = vec![Vec::new(); region_count];
+    for (y, row) in region.iter().enumerate() {
+        for (x, &label) in row.iter().enumerate() {
+            if let Some(id) = label.filter(|&id| touching[id].len() >= 2) {
+                members[id].push((x, y));
+            }
+        }
+    }
+    
+    let neighbors = |x: usize, y: usize| {
+        (-1i32..=1).flat_map(move |dy| (-1i32..=1).map(move |dx| (x as i32 + dx, y as i32 + dy)))
+            .filter(move |&(nx, ny)| (nx, ny) != (x as i32, y as i32)
+                && nx >= 0 && nx < width as i32 && ny >= 0 && ny < height as i32)
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+    };
+    
+    let mut result = vec![vec![false; width]; height];
+    let mut local = vec![vec![usize::MAX; width]; height];
+    for (id, cells) in members.iter().enumerate() {
+        if cells.is_empty() {
+            continue;
+        }
+        for (i, &(x, y)) in cells.iter().enumerate() {
+            local[y][x] = i;
+        }
+        
+        // Steps from each touching core to every cell of the component
+        let distances: Vec<Vec<u32>> = touching[id].iter().map(|&core_id| {
+            let mut distance = vec![u32::MAX; cells.len()];
+            let mut queue = std::collections::VecDeque::new();
+            for (i, &(x, y)) in cells.iter().enumerate() {
+                if neighbors(x, y).any(|(nx, ny)| core[ny][nx] == Some(core_id)) {
+                    distance[i] = 1;
+                    queue.push_back(i);
+                }
+            }
+            while let Some(i) = queue.pop_front() {
+                let (x, y) = cells[i];
+                for (nx, ny) in neighbors(x, y) {
+                    if region[ny][nx] == Some(id) && distance[local[ny][nx]] == u32::MAX {
+                        distance[local[ny][nx]] = distance[i] + 1;
+                        queue.push_back(local[ny][nx]);
+                    }
+                }
+            }
+            distance
+        }).collect();
+        
+        for (a, from_a) in distances.iter().enumerate() {
+            for from_b in &distances[a + 1..] {
+                let sums: Vec<u32> = from_a.iter().zip(from_b).map(|(&da, &db)| da.saturating_add(db)).collect();
+                let shortest = sums.iter().copied().min().unwrap_or(u32::MAX);
+                if shortest == u32::MAX {
+                    continue;
+                }
+                for (&(x, y), &sum) in cells.iter().zip(&sums) {
+                    if sum <= shortest.saturating_add(slack) {
+                        result[y][x] = true;
+                    }
+                }
+            }
+        }
+    }
+    
+    result
+}
\ No newline at end of file
@@ -0,0 +1,53 @@
+use crate::plate_tectonics::Elevation;
+use noise::{NoiseFn, Perlin};
+
+/// Coherent noise the tectonic simulation samples for terrain detail.
+/// `octave` picks an independent layer: 0 is the base elevation, higher
+/// layers are the boundary mountain, inland mountain and abyssal fields, the
+/// two domain-warp offsets and the two plate-boundary offsets.
+/// Samples are expected to lie in -1..1.
+pub trait ElevationNoise: Send + Sync {
+    fn sample(&self, x: f64, y: f64, octave: u32) -> f64;
+    
+    /// Add `weight * sample(xs[i] / divisor, y, 0)` to `out[i]` for every
+    /// element. Override when a whole row can be sampled faster than a cell
+    /// at a time.
+    fn add_row(&self, out: &mut [Elevation], xs: &[f64], divisor: f64, y: f64, weight: Elevation) {
+        for (out, &x) in out.iter_mut().zip(xs) {
+            *out += self.sample(x / divisor, y, 0) as Elevation * weight;
+        }
+    }
+}
+
+/// The default noise: one Perlin generator, 2D for the base layer and
+/// sliced at `z = octave` for the others.
+pub struct PerlinNoise {
+    perlin: Perlin,
+    #[cfg(feature = "fast-noise")]
+    rows: crate::fast_noise::RowPerlin,
+}
+
+impl PerlinNoise {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            #[cfg(feature = "fast-noise")]
+            rows: crate::fast_noise::RowPerlin::new(seed),
+        }
+    }
+}
+
+impl ElevationNoise for PerlinNoise {
+    fn sample(&self, x: f64, y: f64, octave: u32) -> f64 {
+        if octave == 0 {
+            self.perlin.get([x, y])
+        } else {
+            self.perlin.get([x, y, octave as f64])
+        }
+    }
+    
+    #[cfg(feature = "fast-noise")]
+    fn add_row(&self, out: &mut [Elevation], xs: &[f64], divisor: f64, y: f64, weight: Elevation) {
+        self.rows.add_row(out, xs, divisor, y, weight);
+    }
+}
@@ -1,67 +1,665 @@
-use crate::TerrainData;
+use crate::{MapStyle, Projection, TerrainCell, TerrainData};
+#[cfg(feature = "fs")]
+use crate::terrain::{StageSnapshot, StageView};
 use image::{ImageBuffer, Rgb, RgbImage};
+#[cfg(feature = "fs")]
+use image::{GrayImage, Luma};
+#[cfg(feature = "fs")]
 use std::fs::File;
-use std::io::Write;
+#[cfg(feature = "fs")]
+use std::io::{Read, Write};
 
-pub fn export_png(terrain: &TerrainData, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// Presentation settings for the colour PNG exports.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// Integer nearest-neighbour upscale factor, so each cell stays a crisp block.
+    pub scale: u32,
+    /// Hue rotation in degrees applied to ocean, lake and river colours.
+    pub water_tint: f32,
+    /// Write RGBA with water cells fully transparent instead of opaque RGB.
+    pub transparent_water: bool,
+    pub style: MapStyle,
+    /// Opacity of the smallest streams over the land beneath, 0 to 1. Larger
+    /// rivers ramp toward fully opaque; 1 paints every river solid.
+    pub river_opacity: f32,
+    /// Spacing of a labelled grid drawn over the image: degrees of latitude
+    /// and longitude on an equirectangular map, cells otherwise (0 for none).
+    pub graticule: f32,
+    /// Blend each cell's colour with similar neighbours and upscale bilinearly
+    /// instead of in crisp blocks, softening biome seams.
+    pub color_smoothing: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            water_tint: 0.0,
+            transparent_water: false,
+            style: MapStyle::Realistic,
+            river_opacity: 1.0,
+            graticule: 0.0,
+            color_smoothing: false,
+        }
+    }
+}
+
+/// Renders the terrain as a colour PNG, RGBA with clear water if `transparent_water` is set.
+#[cfg(feature = "fs")]
+pub fn export_png(terrain: &TerrainData, filename: &str, options: &RenderOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let scale = options.scale.max(1);
+    let (pixels, color_type) = render_pixels(terrain, options);
+    image::save_buffer(filename, &pixels, terrain.width * scale, terrain.height * scale, color_type)?;
+    Ok(())
+}
+
+/// The PNG `export_png` would write, encoded in memory instead.
+pub fn encode_png(terrain: &TerrainData, options: &RenderOptions) -> Result<Vec<u8>, image::ImageError> {
+    use image::ImageEncoder;
+    
+    let scale = options.scale.max(1);
+    let (pixels, color_type) = render_pixels(terrain, options);
+    let mut bytes = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut bytes)
+        .write_image(&pixels, terrain.width * scale, terrain.height * scale, color_type)?;
+    Ok(bytes)
+}
+
+/// Rendered pixels with their layout: RGB, or RGBA with clear water.
+fn render_pixels(terrain: &TerrainData, options: &RenderOptions) -> (Vec<u8>, image::ColorType) {
+    let scale = options.scale.max(1);
+    let buffer = render_to_rgb_buffer(terrain, options);
+    if !options.transparent_water {
+        return (buffer, image::ColorType::Rgb8);
+    }
+    
+    let width = terrain.width * scale;
+    let rgba = buffer.chunks_exact(3).enumerate().flat_map(|(i, rgb)| {
+        let (x, y) = (i as u32 % width / scale, i as u32 / width / scale);
+        let alpha = if terrain.cells[y as usize][x as usize].is_water { 0 } else { 255 };
+        [rgb[0], rgb[1], rgb[2], alpha]
+    }).collect();
+    (rgba, image::ColorType::Rgba8)
+}
+
+/// The pixels `export_png` would write, as row-major RGB bytes, without
+/// touching the filesystem. The image is `scale` times the grid size.
+pub fn render_to_rgb_buffer(terrain: &TerrainData, options: &RenderOptions) -> Vec<u8> {
+    // Without climate there are no biomes to colour, so show plain elevation
+    let img = if options.style == MapStyle::OldMap {
+        render_old_map(terrain)
+    } else if terrain.generation_params.terrain_only {
+        render_elevation(terrain, &terrain.cells)
+    } else {
+        render_terrain(terrain, &terrain.cells, options)
+    };
+    finish_image(img, terrain, options).into_raw()
+}
+
+/// Scale a grid-sized render up to the output size and draw the overlays.
+fn finish_image(img: RgbImage, terrain: &TerrainData, options: &RenderOptions) -> RgbImage {
+    let mut img = if options.color_smoothing {
+        let img = smooth_colors(&img);
+        let scale = options.scale.max(1);
+        image::imageops::resize(&img, img.width() * scale, img.height() * scale, image::imageops::FilterType::Triangle)
+    } else {
+        upscale(&img, options.scale)
+    };
+    draw_graticule(&mut img, terrain, options);
+    img
+}
+
+/// Average each pixel with its eight neighbours, each weighted by how close
+/// its colour is, so gradual biome seams soften while coasts and rivers,
+/// which differ sharply from what surrounds them, stay crisp.
+fn smooth_colors(img: &RgbImage) -> RgbImage {
+    // RGB distance at which a neighbour stops contributing
+    const SIMILARITY_RANGE: f32 = 80.0;
+    let (width, height) = (img.width() as i32, img.height() as i32);
+    
+    ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+        let center = img.get_pixel(x, y).0.map(|v| v as f32);
+        let (mut total, mut weights) = ([0.0f32; 3], 0.0);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                let color = img.get_pixel(nx as u32, ny as u32).0.map(|v| v as f32);
+                let distance = color.iter().zip(center).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt();
+                let weight = (1.0 - distance / SIMILARITY_RANGE).max(0.0);
+                for (sum, channel) in total.iter_mut().zip(color) {
+                    *sum += channel * weight;
+                }
+                weights += weight;
+            }
+        }
+        Rgb(total.map(|sum| (sum / weights).round() as u8))
+    })
+}
+
+fn render_terrain(terrain: &TerrainData, cells: &[Vec<TerrainCell>], options: &RenderOptions) -> RgbImage {
     let mut img: RgbImage = ImageBuffer::new(terrain.width, terrain.height);
+    let rivers = widen_rivers(cells, terrain.generation_params.topology);
+    let hypsometry = Hypsometry::of(terrain, cells);
     
     for y in 0..terrain.height {
         for x in 0..terrain.width {
-            let cell = &terrain.cells[y as usize][x as usize];
-            let slope = calculate_slope(terrain, x as usize, y as usize);
-            let color = get_realistic_terrain_color(cell, slope);
+            let cell = &cells[y as usize][x as usize];
+            let slope = crate::max_slope(cells, terrain.generation_params.topology, x as usize, y as usize);
+            let river_order = rivers[y as usize][x as usize];
+            let color = get_realistic_terrain_color(cell, y as usize, slope, river_order, &hypsometry, options);
             img.put_pixel(x, y, color);
         }
     }
     
-    img.save(filename)?;
+    img
+}
+
+/// Reference heights for atlas-style tinting: the sea level of each row and
+/// the greatest height of land above its row's sea level.
+struct Hypsometry {
+    sea_levels: Vec<f32>,
+    relief: f32,
+}
+
+impl Hypsometry {
+    fn of(terrain: &TerrainData, cells: &[Vec<TerrainCell>]) -> Self {
+        let sea_levels: Vec<f32> = (0..cells.len()).map(|y| terrain.sea_level_at(y)).collect();
+        let relief = cells.iter().zip(&sea_levels)
+            .flat_map(|(row, &sea_level)| row.iter().filter(|cell| !cell.is_water).map(move |cell| cell.elevation - sea_level))
+            .fold(f32::EPSILON, f32::max);
+        Self { sea_levels, relief }
+    }
+    
+    /// Depth of water in row `y` below its sea level.
+    fn depth(&self, elevation: f32, y: usize) -> f32 {
+        self.sea_levels[y] - elevation
+    }
+    
+    /// Height of land in row `y` above its sea level as a fraction of the
+    /// highest peak's.
+    fn altitude(&self, elevation: f32, y: usize) -> f32 {
+        ((elevation - self.sea_levels[y]) / self.relief).clamp(0.0, 1.0)
+    }
+}
+
+/// Renders a grid captured part-way through generation. `terrain` is the
+/// finished world and supplies the dimensions and altitude lines.
+#[cfg(feature = "fs")]
+pub fn export_stage_png(terrain: &TerrainData, snapshot: &StageSnapshot, filename: &str, options: &RenderOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let img = match snapshot.view {
+        StageView::Terrain => render_terrain(terrain, &snapshot.cells, options),
+        StageView::Elevation => render_elevation(terrain, &snapshot.cells),
+        StageView::Climate => ImageBuffer::from_fn(terrain.width, terrain.height, |x, y| {
+            get_climate_color(&snapshot.cells[y as usize][x as usize])
+        }),
+    };
+    finish_image(img, terrain, options).save(filename)?;
+    Ok(())
+}
+
+/// Elevation colormap for stages before biomes exist: water in depth-shaded
+/// blue, land stretched over its own range from lowland green through brown
+/// to white peaks.
+fn render_elevation(terrain: &TerrainData, cells: &[Vec<TerrainCell>]) -> RgbImage {
+    let land = cells.iter().flatten().filter(|cell| !cell.is_water).map(|cell| cell.elevation);
+    let (low, high) = land.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), e| (lo.min(e), hi.max(e)));
+    let range = (high - low).max(f32::EPSILON);
+    
+    ImageBuffer::from_fn(terrain.width, terrain.height, |x, y| {
+        let cell = &cells[y as usize][x as usize];
+        if cell.is_water {
+            return get_water_color(cell.elevation);
+        }
+        
+        elevation_ramp((cell.elevation - low) / range)
+    })
+}
+
+/// Land colour for a normalized height `t` in [0, 1].
+fn elevation_ramp(t: f32) -> Rgb<u8> {
+    if t < 0.5 {
+        interpolate_color([40, 110, 50], [190, 170, 100], t * 2.0)
+    } else if t < 0.8 {
+        interpolate_color([190, 170, 100], [120, 80, 50], (t - 0.5) / 0.3)
+    } else {
+        interpolate_color([120, 80, 50], [245, 245, 245], (t - 0.8) / 0.2)
+    }
+}
+
+/// Parchment-style map: land posterized into sepia elevation bands, coasts and
+/// rivers inked in, water a flat pale blue. Uses only elevation and water, so
+/// it also works for terrain-only worlds.
+fn render_old_map(terrain: &TerrainData) -> RgbImage {
+    const BANDS: [[u8; 3]; 5] = [
+        [236, 222, 186],
+        [220, 198, 152],
+        [198, 170, 120],
+        [170, 136, 92],
+        [138, 104, 68],
+    ];
+    const WATER: [u8; 3] = [176, 196, 188];
+    const COAST_INK: [u8; 3] = [70, 48, 30];
+    const RIVER_INK: [u8; 3] = [96, 118, 124];
+    
+    let cells = &terrain.cells;
+    let coast = crate::coast_distances(cells, terrain.generation_params.topology);
+    let land = cells.iter().flatten().filter(|cell| !cell.is_water).map(|cell| cell.elevation);
+    let (low, high) = land.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), e| (lo.min(e), hi.max(e)));
+    let range = (high - low).max(f32::EPSILON);
+    
+    ImageBuffer::from_fn(terrain.width, terrain.height, |x, y| {
+        let cell = &cells[y as usize][x as usize];
+        if cell.is_water {
+            return Rgb(WATER);
+        }
+        if coast[y as usize][x as usize] == 1 {
+            return Rgb(COAST_INK);
+        }
+        if cell.has_river {
+            return Rgb(RIVER_INK);
+        }
+        
+        let t = (cell.elevation - low) / range;
+        let band = ((t * BANDS.len() as f32) as usize).min(BANDS.len() - 1);
+        Rgb(BANDS[band])
+    })
+}
+
+/// Animated GIF of elevation frames, such as from
+/// `TerrainGenerator::animate_plates`. Each frame floods the lowest
+/// `water_percentage` of its cells; land uses one shared colour range so
+/// rising mountains show up as they grow.
+#[cfg(feature = "animate")]
+pub fn export_plate_animation(frames: &[Vec<f32>], width: u32, height: u32, water_percentage: f32,
+                              filename: &str, scale: u32) -> Result<(), Box<dyn std::error::Error>> {
+    const FRAME_DELAY: u16 = 10; // hundredths of a second
+    
+    let (low, high) = frames.iter().flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &e| (lo.min(e), hi.max(e)));
+    let range = (high - low).max(f32::EPSILON);
+    let scale = scale.max(1);
+    
+    // GIF stores its dimensions as 16-bit values
+    let gif_size = |cells: u32| cells.checked_mul(scale).and_then(|pixels| u16::try_from(pixels).ok());
+    let (Some(gif_width), Some(gif_height)) = (gif_size(width), gif_size(height)) else {
+        return Err(format!(
+            "{}x{} at scale {} is too large for a GIF (at most {} pixels a side)",
+            width, height, scale, u16::MAX
+        ).into());
+    };
+    
+    let mut encoder = gif::Encoder::new(File::create(filename)?, gif_width, gif_height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    
+    for elevations in frames {
+        let mut sorted = elevations.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let sea_level = crate::percentile_of_sorted(&sorted, water_percentage).unwrap_or(low);
+        
+        let img: RgbImage = ImageBuffer::from_fn(width, height, |x, y| {
+            let elevation = elevations[(y * width + x) as usize];
+            if elevation <= sea_level {
+                get_water_color(elevation)
+            } else {
+                elevation_ramp((elevation - low) / range)
+            }
+        });
+        let img = upscale(&img, scale);
+        
+        let mut frame = gif::Frame::from_rgb_speed(gif_width, gif_height, img.as_raw(), 10);
+        frame.delay = FRAME_DELAY;
+        encoder.write_frame(&frame)?;
+    }
+    
     Ok(())
 }
 
+/// Temperature from blue (cold) to red (hot), pulled toward green where wet.
+#[cfg(feature = "fs")]
+fn get_climate_color(cell: &TerrainCell) -> Rgb<u8> {
+    let Rgb([r, g, b]) = interpolate_color([40, 80, 200], [220, 60, 30], (cell.temperature + 20.0) / 50.0);
+    let wetness = (cell.rainfall / 20.0).clamp(0.0, 1.0) * 0.6;
+    interpolate_color([r, g, b], [40, 170, 60], wetness)
+}
+
+fn upscale<P: image::Pixel>(img: &ImageBuffer<P, Vec<P::Subpixel>>, scale: u32) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let scale = scale.max(1);
+    ImageBuffer::from_fn(img.width() * scale, img.height() * scale, |x, y| *img.get_pixel(x / scale, y / scale))
+}
+
+// Share of white mixed into pixels under a graticule line
+const GRATICULE_STRENGTH: f32 = 0.35;
+
+// 3x5 bitmap digits for graticule labels, one row per byte, high bit leftmost
+const LABEL_GLYPHS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const MINUS_GLYPH: [u8; 5] = [0b000, 0b000, 0b111, 0b000, 0b000];
+const POINT_GLYPH: [u8; 5] = [0b000, 0b000, 0b000, 0b000, 0b010];
+
+/// Faint grid lines every `options.graticule` with their values written
+/// along the top and left edges. On an equirectangular map the lines are
+/// meridians and parallels counted out from 0 degrees, with the poles at the
+/// centres of the first and last rows as in the climate simulation; otherwise
+/// they are cell columns and rows.
+fn draw_graticule(img: &mut RgbImage, terrain: &TerrainData, options: &RenderOptions) {
+    let spacing = options.graticule;
+    if spacing <= 0.0 {
+        return;
+    }
+    let (width, height) = (img.width(), img.height());
+    let (x_pixels, y_pixels) = (width as f32, height as f32);
+    let (meridians, parallels) = if terrain.generation_params.projection == Projection::Equirectangular {
+        let half_row = y_pixels / terrain.height as f32 / 2.0;
+        (graticule_lines((-180.0, 0.0), (180.0, x_pixels), spacing, x_pixels),
+         graticule_lines((90.0, half_row), (-90.0, y_pixels - half_row), spacing, y_pixels))
+    } else {
+        (graticule_lines((0.0, 0.0), (terrain.width as f32, x_pixels), spacing, x_pixels),
+         graticule_lines((0.0, 0.0), (terrain.height as f32, y_pixels), spacing, y_pixels))
+    };
+    
+    let lighten = |pixel: &mut Rgb<u8>| {
+        for channel in pixel.0.iter_mut() {
+            *channel = (*channel as f32 + (255.0 - *channel as f32) * GRATICULE_STRENGTH).round() as u8;
+        }
+    };
+    for &(x, _) in &meridians {
+        (0..height).for_each(|y| lighten(img.get_pixel_mut(x, y)));
+    }
+    for &(y, _) in &parallels {
+        (0..width).for_each(|x| lighten(img.get_pixel_mut(x, y)));
+    }
+    
+    let size = options.scale.max(1);
+    for (x, label) in meridians {
+        draw_label(img, x + 2 * size, size, &label, size);
+    }
+    for (y, label) in parallels {
+        draw_label(img, size, y + 2 * size, &label, size);
+    }
+}
+
+/// Pixel positions and labels of the multiples of `spacing` strictly between
+/// two values, each given with the pixel it falls on. Labels carry as many
+/// decimal places as `spacing` needs, up to three.
+fn graticule_lines(start: (f32, f32), end: (f32, f32), spacing: f32, pixels: f32) -> Vec<(u32, String)> {
+    let ((start, start_pixel), (end, end_pixel)) = (start, end);
+    let (low, high) = (start.min(end), start.max(end));
+    let decimals = (0..3)
+        .find(|&places| {
+            let shifted = spacing * 10f32.powi(places);
+            (shifted - shifted.round()).abs() < 1e-3 * shifted.max(1.0)
+        })
+        .unwrap_or(3) as usize;
+    
+    let first = (low / spacing).floor() as i32 + 1;
+    (first..).map(|k| k as f32 * spacing)
+        .take_while(|&value| value < high)
+        .map(|value| {
+            let pixel = start_pixel + (value - start) / (end - start) * (end_pixel - start_pixel);
+            // Format zero as "0", never "-0"
+            (pixel as u32, format!("{:.*}", decimals, value + 0.0))
+        })
+        .filter(|&(pixel, _)| (pixel as f32) < pixels)
+        .collect()
+}
+
+/// Write `text` in white bitmap digits, `size` pixels per font pixel, with
+/// its top-left corner at (`x`, `y`), clipped to the image.
+fn draw_label(img: &mut RgbImage, x: u32, y: u32, text: &str, size: u32) {
+    for (i, ch) in text.chars().enumerate() {
+        let glyph = match ch {
+            '-' => MINUS_GLYPH,
+            '.' => POINT_GLYPH,
+            digit => LABEL_GLYPHS[digit.to_digit(10).unwrap_or(0) as usize],
+        };
+        let left = x + i as u32 * 4 * size;
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..size {
+                    for dx in 0..size {
+                        let (px, py) = (left + column * size + dx, y + row as u32 * size + dy);
+                        if px < img.width() && py < img.height() {
+                            img.put_pixel(px, py, Rgb([255, 255, 255]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
 fn calculate_slope(terrain: &TerrainData, x: usize, y: usize) -> f32 {
-    let current_elevation = terrain.cells[y][x].elevation;
-    let mut max_slope: f32 = 0.0;
+    crate::max_slope(&terrain.cells, terrain.generation_params.topology, x, y)
+}
+
+/// Grayscale slope map: flat ground is black and the steepest 1% of cells are
+/// white, with a linear ramp in between.
+#[cfg(feature = "fs")]
+pub fn export_slope_map(terrain: &TerrainData, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let slopes: Vec<f32> = terrain.iter_cells()
+        .map(|(x, y, _)| calculate_slope(terrain, x, y))
+        .collect();
     
-    for dy in -1i32..=1 {
-        for dx in -1i32..=1 {
-            if dx == 0 && dy == 0 { continue; }
-            
-            let nx = x as i32 + dx;
-            let ny = y as i32 + dy;
+    let mut sorted = slopes.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let white_point = crate::percentile_of_sorted(&sorted, 99.0).unwrap_or(0.0).max(f32::EPSILON);
+    
+    let mut img: GrayImage = ImageBuffer::new(terrain.width, terrain.height);
+    for (i, slope) in slopes.iter().enumerate() {
+        let value = (slope / white_point).min(1.0) * 255.0;
+        img.put_pixel(i as u32 % terrain.width, i as u32 / terrain.width, Luma([value as u8]));
+    }
+    
+    img.save(filename)?;
+    Ok(())
+}
+
+/// Grayscale distance-to-coast map: land brightens from mid-gray with distance
+/// inland, water darkens with distance offshore, each scaled to its own maximum.
+#[cfg(feature = "fs")]
+pub fn export_coast_distance_map(terrain: &TerrainData, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let distances = crate::coast_distances(&terrain.cells, terrain.generation_params.topology);
+    let farthest = |water: bool| terrain.iter_cells()
+        .filter(|(_, _, cell)| cell.is_water == water)
+        .map(|(x, y, _)| distances[y][x])
+        .filter(|&distance| distance != u32::MAX)
+        .max()
+        .unwrap_or(1)
+        .max(1) as f32;
+    let (land_max, water_max) = (farthest(false), farthest(true));
+    
+    let mut img: GrayImage = ImageBuffer::new(terrain.width, terrain.height);
+    for (x, y, cell) in terrain.iter_cells() {
+        let distance = distances[y][x].min(u32::MAX - 1) as f32;
+        let value = if cell.is_water {
+            127.0 * (1.0 - (distance / water_max).min(1.0))
+        } else {
+            128.0 + 127.0 * (distance / land_max).min(1.0)
+        };
+        img.put_pixel(x as u32, y as u32, Luma([value as u8]));
+    }
+    
+    img.save(filename)?;
+    Ok(())
+}
+
+/// D8 drainage map: each land cell is coloured by the direction water leaves
+/// it, going round the hue wheel with the compass (east red). Land pits
+/// with nowhere lower to drain are white and water is dark grey, so breaks
+/// in the drainage stand out.
+#[cfg(feature = "fs")]
+pub fn export_flow_direction_map(terrain: &TerrainData, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    const EAST: Rgb<u8> = Rgb([220, 60, 60]);
+    
+    let rivers = crate::rivers::RiverGenerator::new(terrain.width, terrain.height, &terrain.generation_params);
+    let receivers = rivers.flow_directions(&terrain.cells);
+    let hex = terrain.generation_params.topology == crate::Topology::Hex;
+    let pole = |y: usize| terrain.generation_params.projection == Projection::Equirectangular
+        && (y == 0 || y + 1 == terrain.height as usize);
+    
+    let mut img: RgbImage = ImageBuffer::new(terrain.width, terrain.height);
+    for (x, y, cell) in terrain.iter_cells() {
+        let color = match receivers[y][x] {
+            _ if cell.is_water => Rgb([40, 40, 40]),
+            None if pole(y) => Rgb([40, 40, 40]),
+            None => Rgb([255, 255, 255]),
+            Some((nx, ny)) => {
+                // Odd hex rows sit half a cell to the right
+                let row_shift = if hex { ((ny % 2) as f32 - (y % 2) as f32) * 0.5 } else { 0.0 };
+                let dx = nx as f32 - x as f32 + row_shift;
+                let dy = y as f32 - ny as f32;
+                shift_hue(EAST, dy.atan2(dx).to_degrees())
+            }
+        };
+        img.put_pixel(x as u32, y as u32, color);
+    }
+    
+    img.save(filename)?;
+    Ok(())
+}
+
+/// Stream order of the river drawn over each cell, 0 for none. Rivers of
+/// order 3 and up spill onto neighbouring land so major rivers read as wider.
+fn widen_rivers(cells: &[Vec<TerrainCell>], topology: crate::Topology) -> Vec<Vec<u8>> {
+    const WIDE_RIVER_ORDER: u8 = 3;
+    
+    let height = cells.len();
+    let width = cells[0].len();
+    let mut drawn = vec![vec![0u8; width]; height];
+    
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if !cell.has_river {
+                continue;
+            }
+            // Terrain loaded from older JSON has rivers without orders
+            let order = cell.river_order.max(1);
+            drawn[y][x] = drawn[y][x].max(order);
             
-            if nx >= 0 && nx < terrain.width as i32 && ny >= 0 && ny < terrain.height as i32 {
-                let neighbor_elevation = terrain.cells[ny as usize][nx as usize].elevation;
-                let elevation_diff = (current_elevation - neighbor_elevation).abs();
-                let distance = ((dx * dx + dy * dy) as f32).sqrt();
-                let slope = elevation_diff / distance;
-                max_slope = max_slope.max(slope);
+            if order >= WIDE_RIVER_ORDER {
+                for (nx, ny, _) in topology.neighbors(x, y, width, height) {
+                    if !cells[ny][nx].is_water {
+                        drawn[ny][nx] = drawn[ny][nx].max(order);
+                    }
+                }
             }
         }
     }
     
-    max_slope
+    drawn
 }
 
-fn get_realistic_terrain_color(cell: &crate::TerrainCell, slope: f32) -> Rgb<u8> {
+fn get_realistic_terrain_color(cell: &TerrainCell, y: usize, slope: f32, river_order: u8, hypsometry: &Hypsometry,
+                               options: &RenderOptions) -> Rgb<u8> {
+    if cell.biome == crate::BiomeType::SeaIce {
+        return get_sea_ice_color(cell.temperature);
+    }
+    
+    if cell.is_water && cell.is_freshwater {
+        return shift_hue(get_lake_color(cell.elevation), options.water_tint);
+    }
+    
     if cell.is_water {
-        return get_water_color(cell.elevation);
+        return shift_hue(get_bathymetric_color(hypsometry.depth(cell.elevation, y)), options.water_tint);
+    }
+    
+    let land = get_land_color(cell, slope, hypsometry.altitude(cell.elevation, y));
+    if river_order == 0 {
+        return land;
+    }
+    
+    // Stream order stands in for flow: order 5 and up is drawn solid
+    let opacity = options.river_opacity.clamp(0.0, 1.0);
+    let alpha = opacity + (1.0 - opacity) * ((river_order - 1) as f32 / 4.0).min(1.0);
+    let Rgb(river) = shift_hue(get_river_color(cell, river_order), options.water_tint);
+    if alpha >= 1.0 {
+        return Rgb(river);
+    }
+    interpolate_color(land.0, river, alpha)
+}
+
+fn get_land_color(cell: &TerrainCell, slope: f32, altitude: f32) -> Rgb<u8> {
+    // Share of the atlas altitude tint mixed into the climate colour
+    const HYPSOMETRIC_TINT: f32 = 0.35;
+    
+    // Ice cap, whether above the snow line or forced by the polar bias
+    if cell.biome == crate::BiomeType::Glacier {
+        return apply_elevation_shading(Rgb([235, 240, 245]), cell.elevation, slope);
     }
     
-    if cell.has_river {
-        return get_river_color(cell.elevation);
+    // Dark canopy over muddy tidal flats, distinct from inland rainforest
+    if cell.biome == crate::BiomeType::Mangrove {
+        return apply_elevation_shading(Rgb([60, 72, 40]), cell.elevation, slope);
     }
     
     // Calculate vegetation density based on rainfall, temperature, and elevation
     let vegetation_density = calculate_vegetation_density(cell);
     
     // Get base terrain color based on elevation and moisture
-    let base_color = get_base_terrain_color(cell, vegetation_density);
+    let Rgb(base_color) = get_base_terrain_color(cell, vegetation_density);
+    let Rgb(tint) = get_hypsometric_color(altitude);
+    let tinted = interpolate_color(base_color, tint, HYPSOMETRIC_TINT);
     
     // Apply elevation shading
-    let shaded_color = apply_elevation_shading(base_color, cell.elevation, slope);
-    
-    shaded_color
+    apply_elevation_shading(tinted, cell.elevation, slope)
+}
+
+/// Conventional atlas land tint for an altitude from 0 (coast) to 1 (highest
+/// peak): sand at the shore, then lowland green, upland tan, brown and snow.
+fn get_hypsometric_color(altitude: f32) -> Rgb<u8> {
+    const STOPS: [(f32, [u8; 3]); 6] = [
+        (0.0, [214, 200, 150]),
+        (0.03, [110, 160, 90]),
+        (0.3, [190, 180, 110]),
+        (0.6, [160, 115, 70]),
+        (0.85, [120, 90, 70]),
+        (1.0, [245, 245, 245]),
+    ];
+    color_stops(&STOPS, altitude)
+}
+
+/// Graded ocean blues by depth below sea level, in elevation units: pale over
+/// the continental shelf, darkening through the deep ocean to the abyssal plain.
+fn get_bathymetric_color(depth: f32) -> Rgb<u8> {
+    const STOPS: [(f32, [u8; 3]); 4] = [
+        (0.0, [130, 185, 220]),
+        (0.05, [80, 145, 200]),
+        (0.2, [35, 85, 160]),
+        (0.4, [15, 40, 100]),
+    ];
+    color_stops(&STOPS, depth)
+}
+
+/// Piecewise-linear colour ramp through `(position, colour)` stops in
+/// ascending order, clamped at both ends.
+fn color_stops(stops: &[(f32, [u8; 3])], t: f32) -> Rgb<u8> {
+    let upper = stops.iter().position(|&(position, _)| position > t).unwrap_or(stops.len());
+    if upper == 0 {
+        return Rgb(stops[0].1);
+    }
+    if upper == stops.len() {
+        return Rgb(stops[stops.len() - 1].1);
+    }
+    let (low, low_color) = stops[upper - 1];
+    let (high, high_color) = stops[upper];
+    interpolate_color(low_color, high_color, (t - low) / (high - low))
 }
 
 fn get_water_color(elevation: f32) -> Rgb<u8> {
@@ -71,15 +669,64 @@ fn get_water_color(elevation: f32) -> Rgb<u8> {
     Rgb([0, green_component, blue_intensity])
 }
 
-fn get_river_color(elevation: f32) -> Rgb<u8> {
+/// Lakes are shallower-looking than the sea: lighter and greener.
+fn get_lake_color(elevation: f32) -> Rgb<u8> {
+    let depth_factor = (1.0 - elevation.max(0.0)).clamp(0.0, 1.0);
+    interpolate_color([40, 130, 150], [20, 80, 120], depth_factor)
+}
+
+/// Rotate a colour's hue by `degrees`, keeping its lightness and saturation.
+fn shift_hue(color: Rgb<u8>, degrees: f32) -> Rgb<u8> {
+    if degrees == 0.0 {
+        return color;
+    }
+    
+    // Rotation about the grey axis of RGB space
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let third: f32 = 1.0 / 3.0;
+    let root = third.sqrt();
+    let a = cos + (1.0 - cos) * third;
+    let b = third * (1.0 - cos) - root * sin;
+    let c = third * (1.0 - cos) + root * sin;
+    
+    let [r, g, bl] = color.0.map(|v| v as f32);
+    let rotate = |x: f32, y: f32, z: f32| (x * a + y * b + z * c).clamp(0.0, 255.0) as u8;
+    Rgb([rotate(r, g, bl), rotate(g, bl, r), rotate(bl, r, g)])
+}
+
+fn get_sea_ice_color(temperature: f32) -> Rgb<u8> {
+    // Thin ice near the freezing point shows a blue tint; colder ice is whiter
+    let thickness = (-temperature / 20.0).clamp(0.0, 1.0);
+    interpolate_color([180, 205, 225], [235, 242, 250], thickness)
+}
+
+fn get_river_color(cell: &TerrainCell, order: u8) -> Rgb<u8> {
+    use crate::BiomeType;
+    
     // Make rivers clearly visible as flowing water
+    let elevation = cell.elevation;
     let flow_factor = (1.0 - elevation * 0.2).max(0.4);
     let blue = (120.0 + flow_factor * 120.0) as u8;
     let green = (60.0 + flow_factor * 40.0) as u8;
-    Rgb([10, green, blue])
+    
+    // Higher-order rivers deepen toward a saturated blue
+    let depth = (order.saturating_sub(1) as f32 * 0.15).min(0.6);
+    let Rgb(clear) = interpolate_color([10, green, blue], [10, 50, 170], depth);
+    
+    // Lowland rivers carry silt, most of all through dry open country and
+    // deltas; mountain and ice-fed streams stay clear
+    let lowland = (1.0 - elevation / 0.8).clamp(0.0, 1.0);
+    let sediment = match cell.biome {
+        BiomeType::Desert | BiomeType::Beach => 1.0,
+        BiomeType::Grassland => 0.7,
+        BiomeType::Forest | BiomeType::Rainforest => 0.4,
+        BiomeType::Mountain | BiomeType::Tundra | BiomeType::Glacier => 0.0,
+        _ => 0.5,
+    };
+    interpolate_color(clear, [120, 105, 70], lowland * sediment * 0.6)
 }
 
-fn calculate_vegetation_density(cell: &crate::TerrainCell) -> f32 {
+fn calculate_vegetation_density(cell: &TerrainCell) -> f32 {
     let temp_factor = if cell.temperature > -5.0 && cell.temperature < 40.0 {
         let optimal_temp = 20.0;
         1.0 - (cell.temperature - optimal_temp).abs() / 30.0
@@ -90,17 +737,21 @@ fn calculate_vegetation_density(cell: &crate::TerrainCell) -> f32 {
     let rainfall_factor = (cell.rainfall / 15.0).min(1.0);
     let elevation_factor = (1.0 - (cell.elevation / 3.0)).max(0.0);
     
-    (temp_factor * rainfall_factor * elevation_factor).max(0.0).min(1.0)
+    (temp_factor * rainfall_factor * elevation_factor).clamp(0.0, 1.0)
 }
 
-fn get_base_terrain_color(cell: &crate::TerrainCell, vegetation_density: f32) -> Rgb<u8> {
+fn get_base_terrain_color(cell: &TerrainCell, vegetation_density: f32) -> Rgb<u8> {
+    // Snow fades in over the temperature drop of half an elevation unit above freezing
+    const SNOW_FADE: f32 = 0.5 * crate::climate::LAPSE_RATE;
+    
     let elevation = cell.elevation;
     let temperature = cell.temperature;
     let rainfall = cell.rainfall;
     
-    // High elevation - rocky/snowy
-    if elevation > 2.0 {
-        let snow_factor = ((elevation - 2.0) / 1.0).min(1.0);
+    // High or cold hilly ground is rock, snow-covered only where the cell
+    // itself is near or below freezing, so hot tropical peaks stay bare
+    let snow_factor = (1.0 - temperature / SNOW_FADE).clamp(0.0, 1.0);
+    if elevation > 2.0 || (snow_factor > 0.0 && elevation > 0.5) {
         let rock_gray = 120;
         let snow_white = 240;
         let gray_value = (rock_gray as f32 + (snow_white - rock_gray) as f32 * snow_factor) as u8;
@@ -185,9 +836,369 @@ fn interpolate_color(color1: [u8; 3], color2: [u8; 3], factor: f32) -> Rgb<u8> {
     Rgb([r, g, b])
 }
 
+#[cfg(feature = "fs")]
 pub fn export_json(terrain: &TerrainData, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
     let json_data = serde_json::to_string_pretty(terrain)?;
     let mut file = File::create(filename)?;
     file.write_all(json_data.as_bytes())?;
     Ok(())
+}
+
+/// Write the same-biome region graph from `regions::biome_regions` as JSON.
+#[cfg(feature = "fs")]
+pub fn export_region_graph(terrain: &TerrainData, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let regions = crate::regions::biome_regions(terrain);
+    File::create(filename)?.write_all(serde_json::to_string_pretty(&regions)?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(feature = "fs")]
+pub fn import_json(filename: &str) -> Result<TerrainData, Box<dyn std::error::Error>> {
+    let mut json_data = String::new();
+    File::open(filename)?.read_to_string(&mut json_data)?;
+    Ok(serde_json::from_str(&json_data)?)
+}
+
+#[derive(Debug, Default)]
+pub struct DiffSummary {
+    pub total_cells: usize,
+    pub changed_cells: usize,
+    pub water_changed: usize,
+    pub biome_changed: usize,
+    pub river_changed: usize,
+}
+
+/// Render cells that differ between two terrains of equal size. Unchanged cells
+/// are a dimmed grayscale of the first terrain's elevation; land/water flips are
+/// red, other biome changes yellow and river-only changes cyan.
+#[cfg(feature = "fs")]
+pub fn export_diff_png(terrain: &TerrainData, other: &TerrainData, filename: &str) -> Result<DiffSummary, Box<dyn std::error::Error>> {
+    if terrain.width != other.width || terrain.height != other.height {
+        return Err(format!(
+            "cannot diff {}x{} terrain against {}x{}",
+            terrain.width, terrain.height, other.width, other.height
+        ).into());
+    }
+    
+    let mut img: RgbImage = ImageBuffer::new(terrain.width, terrain.height);
+    let mut summary = DiffSummary {
+        total_cells: (terrain.width * terrain.height) as usize,
+        ..DiffSummary::default()
+    };
+    
+    for (x, y, a) in terrain.iter_cells() {
+        let b = &other.cells[y][x];
+        
+        let water_changed = a.is_water != b.is_water;
+        let biome_changed = a.biome != b.biome;
+        let river_changed = a.has_river != b.has_river;
+        
+        if water_changed { summary.water_changed += 1; }
+        if biome_changed { summary.biome_changed += 1; }
+        if river_changed { summary.river_changed += 1; }
+        if water_changed || biome_changed || river_changed {
+            summary.changed_cells += 1;
+        }
+        
+        let color = if water_changed {
+            Rgb([230, 40, 40])
+        } else if biome_changed {
+            Rgb([240, 210, 40])
+        } else if river_changed {
+            Rgb([40, 220, 230])
+        } else {
+            let gray = (40.0 + a.elevation.clamp(0.0, 3.0) / 3.0 * 80.0) as u8;
+            Rgb([gray, gray, gray])
+        };
+        img.put_pixel(x as u32, y as u32, color);
+    }
+    
+    img.save(filename)?;
+    Ok(summary)
+}
+
+/// Write elevation as raw little-endian binary for fast loading elsewhere.
+///
+/// Layout: `width` as u32, `height` as u32, then `width * height` f32
+/// elevations in row-major order (row 0 first, `x` varying fastest). With
+/// numpy: `w, h = np.fromfile(f, "<u4", 2)` then
+/// `np.fromfile(f, "<f4", offset=8).reshape(h, w)`.
+///
+/// With `flat_water`, water cells hold their water body's surface instead of
+/// the ground beneath it, as for `export_geotiff`.
+#[cfg(feature = "fs")]
+pub fn export_raw_elevation(terrain: &TerrainData, filename: &str, flat_water: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let elevations = heightmap_elevations(terrain, flat_water);
+    let mut bytes = Vec::with_capacity(8 + elevations.len() * 4);
+    bytes.extend_from_slice(&terrain.width.to_le_bytes());
+    bytes.extend_from_slice(&terrain.height.to_le_bytes());
+    for elevation in elevations {
+        bytes.extend_from_slice(&elevation.to_le_bytes());
+    }
+    
+    File::create(filename)?.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Row-major cell elevations for heightmap exports. With `flat_water` each
+/// water cell is raised to the surface of its water body, taken as the
+/// body's highest cell, so the ocean sits flat at sea level and lakes at
+/// their own level for engines that draw water separately.
+#[cfg(feature = "fs")]
+fn heightmap_elevations(terrain: &TerrainData, flat_water: bool) -> Vec<f32> {
+    let mut surfaces = vec![f32::NEG_INFINITY; terrain.water_bodies.len()];
+    if flat_water {
+        for (_, _, cell) in terrain.iter_cells() {
+            if let Some(surface) = cell.water_body_id.and_then(|id| surfaces.get_mut(id)) {
+                *surface = surface.max(cell.elevation);
+            }
+        }
+    }
+    
+    terrain.iter_cells().map(|(_, _, cell)| {
+        match cell.water_body_id.and_then(|id| surfaces.get(id)) {
+            Some(&surface) if flat_water && cell.is_water => surface.max(cell.elevation),
+            _ => cell.elevation,
+        }
+    }).collect()
+}
+
+/// Geographic extent of the map as `min_lon,min_lat,max_lon,max_lat` in degrees
+/// (WGS 84). Cell (0, 0) is the north-west corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoBounds {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl Default for GeoBounds {
+    fn default() -> Self {
+        Self { min_lon: -180.0, min_lat: -90.0, max_lon: 180.0, max_lat: 90.0 }
+    }
+}
+
+impl std::str::FromStr for GeoBounds {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s.split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("invalid bounding box '{}': {}", s, e))?;
+        
+        let [min_lon, min_lat, max_lon, max_lat] = values[..] else {
+            return Err(format!("bounding box needs 4 values, got {}", values.len()));
+        };
+        if min_lon >= max_lon || min_lat >= max_lat {
+            return Err(format!("bounding box '{}' has min >= max", s));
+        }
+        
+        Ok(Self { min_lon, min_lat, max_lon, max_lat })
+    }
+}
+
+/// Write elevation as a single-band 32-bit float GeoTIFF georeferenced to
+/// `bounds` in EPSG:4326, so it opens with the right extent in GIS tools.
+/// `flat_water` levels water to its surface as for `export_raw_elevation`.
+#[cfg(feature = "fs")]
+pub fn export_geotiff(terrain: &TerrainData, filename: &str, bounds: GeoBounds, flat_water: bool) -> Result<(), Box<dyn std::error::Error>> {
+    use tiff::encoder::{colortype, TiffEncoder};
+    use tiff::tags::Tag;
+    
+    const MODEL_PIXEL_SCALE_TAG: u16 = 33550;
+    const MODEL_TIEPOINT_TAG: u16 = 33922;
+    const GEO_KEY_DIRECTORY_TAG: u16 = 34735;
+    
+    let elevations = heightmap_elevations(terrain, flat_water);
+    
+    let pixel_width = (bounds.max_lon - bounds.min_lon) / terrain.width as f64;
+    let pixel_height = (bounds.max_lat - bounds.min_lat) / terrain.height as f64;
+    
+    // Key directory header (version 1.1.0, 3 keys), then one key per row:
+    // model type geographic, raster pixel-is-area, datum WGS 84
+    let geo_keys: [u16; 16] = [
+        1, 1, 0, 3,
+        1024, 0, 1, 2,
+        1025, 0, 1, 1,
+        2048, 0, 1, 4326,
+    ];
+    
+    let file = File::create(filename)?;
+    let mut encoder = TiffEncoder::new(file)?;
+    let mut image = encoder.new_image::<colortype::Gray32Float>(terrain.width, terrain.height)?;
+    
+    image.encoder().write_tag(Tag::Unknown(MODEL_PIXEL_SCALE_TAG), &[pixel_width, pixel_height, 0.0][..])?;
+    image.encoder().write_tag(Tag::Unknown(MODEL_TIEPOINT_TAG), &[0.0, 0.0, 0.0, bounds.min_lon, bounds.max_lat, 0.0][..])?;
+    image.encoder().write_tag(Tag::Unknown(GEO_KEY_DIRECTORY_TAG), &geo_keys[..])?;
+    image.write_data(&elevations)?;
+    
+    Ok(())
+}
+
+/// Write plate boundaries as GeoJSON LineStrings in EPSG:4326 over `bounds`.
+/// Boundaries follow cell edges between cells of different plates, one or
+/// more lines per pair of plates. Each line carries both plate ids and types,
+/// and whether the plates close (`convergent`) or open (`divergent`) across it
+/// on average, judged by the same velocity test as the tectonics stage.
+#[cfg(feature = "fs")]
+pub fn export_plate_boundaries_geojson(terrain: &TerrainData, filename: &str, bounds: GeoBounds) -> Result<(), Box<dyn std::error::Error>> {
+    use std::collections::{BTreeMap, HashMap};
+    
+    // Edge segments between grid vertices, grouped by the (lower, higher) plate
+    // pair, each with the unit normal pointing from the lower plate's cell
+    type Vertex = (usize, usize);
+    type Segment = (Vertex, Vertex, (f32, f32));
+    let mut segments: BTreeMap<(usize, usize), Vec<Segment>> = BTreeMap::new();
+    let width = terrain.width as usize;
+    let height = terrain.height as usize;
+    for (x, y, cell) in terrain.iter_cells() {
+        if x + 1 < width {
+            let other = terrain.cells[y][x + 1].plate_id;
+            if other != cell.plate_id {
+                let normal = if cell.plate_id < other { (1.0, 0.0) } else { (-1.0, 0.0) };
+                segments.entry((cell.plate_id.min(other), cell.plate_id.max(other)))
+                    .or_default().push(((x + 1, y), (x + 1, y + 1), normal));
+            }
+        }
+        if y + 1 < height {
+            let other = terrain.cells[y + 1][x].plate_id;
+            if other != cell.plate_id {
+                let normal = if cell.plate_id < other { (0.0, 1.0) } else { (0.0, -1.0) };
+                segments.entry((cell.plate_id.min(other), cell.plate_id.max(other)))
+                    .or_default().push(((x, y + 1), (x + 1, y + 1), normal));
+            }
+        }
+    }
+    
+    let plate = |id: usize| terrain.plates.iter().find(|plate| plate.id == id);
+    let pixel_width = (bounds.max_lon - bounds.min_lon) / terrain.width as f64;
+    let pixel_height = (bounds.max_lat - bounds.min_lat) / terrain.height as f64;
+    let to_lon_lat = |(vx, vy): Vertex| {
+        serde_json::json!([bounds.min_lon + vx as f64 * pixel_width, bounds.max_lat - vy as f64 * pixel_height])
+    };
+    
+    let mut features = Vec::new();
+    for ((a, b), pair_segments) in &segments {
+        // Chain segments into polylines through shared vertices
+        let mut at_vertex: HashMap<Vertex, Vec<usize>> = HashMap::new();
+        for (i, &(start, end, _)) in pair_segments.iter().enumerate() {
+            at_vertex.entry(start).or_default().push(i);
+            at_vertex.entry(end).or_default().push(i);
+        }
+        let mut used = vec![false; pair_segments.len()];
+        let next_unused = |vertex: Vertex, used: &mut Vec<bool>| {
+            let i = *at_vertex.get(&vertex)?.iter().find(|&&i| !used[i])?;
+            used[i] = true;
+            let (start, end, _) = pair_segments[i];
+            Some((i, if start == vertex { end } else { start }))
+        };
+        
+        for first in 0..pair_segments.len() {
+            if used[first] {
+                continue;
+            }
+            used[first] = true;
+            let (start, end, _) = pair_segments[first];
+            let mut members = vec![first];
+            let mut forward = vec![start, end];
+            while let Some((i, vertex)) = next_unused(*forward.last().unwrap(), &mut used) {
+                members.push(i);
+                forward.push(vertex);
+            }
+            let mut line = vec![start];
+            while let Some((i, vertex)) = next_unused(*line.last().unwrap(), &mut used) {
+                members.push(i);
+                line.push(vertex);
+            }
+            line.reverse();
+            line.extend_from_slice(&forward[1..]);
+            
+            let (Some(plate_a), Some(plate_b)) = (plate(*a), plate(*b)) else { continue };
+            let convergence = members.iter()
+                .map(|&i| crate::plate_tectonics::convergence_speed(plate_a, plate_b, pair_segments[i].2))
+                .sum::<f32>() / members.len() as f32;
+            
+            features.push(serde_json::json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": line.into_iter().map(to_lon_lat).collect::<Vec<_>>(),
+                },
+                "properties": {
+                    "plates": [a, b],
+                    "plate_types": [plate_a.plate_type, plate_b.plate_type],
+                    "boundary": if convergence > 0.0 { "convergent" } else { "divergent" },
+                    "convergence": convergence,
+                },
+            }));
+        }
+    }
+    
+    let collection = serde_json::json!({ "type": "FeatureCollection", "features": features });
+    File::create(filename)?.write_all(serde_json::to_string(&collection)?.as_bytes())?;
+    Ok(())
+}
+
+/// A straight line across the map from (x0, y0) to (x1, y1), in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileLine {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl std::str::FromStr for ProfileLine {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<usize> = s.split(',')
+            .map(|part| part.trim().parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("invalid profile line '{}': {}", s, e))?;
+        
+        let [x0, y0, x1, y1] = values[..] else {
+            return Err(format!("profile line needs 4 values, got {}", values.len()));
+        };
+        Ok(Self { x0, y0, x1, y1 })
+    }
+}
+
+/// Write an elevation cross-section along `line` as CSV, one row per cell
+/// visited by a Bresenham walk. Endpoints are clamped to the map. Distance is
+/// in cells from the start; `sea_level` is that of the cell's row.
+#[cfg(feature = "fs")]
+pub fn export_profile_csv(terrain: &TerrainData, line: ProfileLine, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let clamp = |v: usize, size: u32| v.min(size as usize - 1) as isize;
+    let (x0, y0) = (clamp(line.x0, terrain.width), clamp(line.y0, terrain.height));
+    let (x1, y1) = (clamp(line.x1, terrain.width), clamp(line.y1, terrain.height));
+    
+    let mut csv = String::from("distance,x,y,elevation,sea_level,is_water,has_river\n");
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+    loop {
+        let cell = &terrain.cells[y as usize][x as usize];
+        let distance = (((x - x0).pow(2) + (y - y0).pow(2)) as f32).sqrt();
+        csv.push_str(&format!("{:.3},{},{},{},{},{},{}\n", distance, x, y, cell.elevation, terrain.sea_level_at(y as usize),
+            cell.is_water as u8, cell.has_river as u8));
+        
+        if x == x1 && y == y1 {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+    
+    File::create(filename)?.write_all(csv.as_bytes())?;
+    Ok(())
 }
\ No newline at end of file
@@ -1,91 +1,224 @@
-use crate::{TerrainCell, TectonicPlate, PlateType};
-use rand::{Rng, SeedableRng};
-use rand::rngs::StdRng;
-use noise::{NoiseFn, Perlin};
+use crate::{TerrainCell, TectonicPlate, PlateType, GenerationParams};
+use crate::seed;
+use rand::Rng;
+use crate::noise_source::{ElevationNoise, PerlinNoise};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Precision tectonic elevation is accumulated in before it is stored in the
+/// `f32` cell field.
+#[cfg(feature = "f64-elevation")]
+pub type Elevation = f64;
+#[cfg(not(feature = "f64-elevation"))]
+pub type Elevation = f32;
+
+/// An authored plate that replaces a random one. The centre is in cells of the
+/// full-size map; without a velocity one is drawn as for random plates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlateSeed {
+    pub x: f32,
+    pub y: f32,
+    pub plate_type: PlateType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<(f32, f32)>,
+}
+
+/// Read a list of plate seeds from a JSON file.
+#[cfg(feature = "fs")]
+pub fn load_plate_seeds(filename: &str) -> Result<Vec<PlateSeed>, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(filename)?)?)
+}
 
 pub struct PlateSimulator {
     width: u32,
     height: u32,
-    rng: StdRng,
-    noise: Perlin,
+    seed: u64,
+    noise: Arc<dyn ElevationNoise>,
+    // World units per cell. Plate placement and noise sampling work in world
+    // units so a downscaled grid reproduces the same world at lower resolution.
+    world_scale: f32,
+    plate_speed: f32,
+    continental_fraction: f32,
+    oceanic_flip_chance: f64,
+    boundary_width: usize,
+    continent_spread: f32,
+    continent_jitter: f32,
+    elevation_exponent: f32,
+    warp_strength: f32,
+    boundary_roughness: f32,
+    pinned_plates: Vec<PlateSeed>,
+    top_up_plates: bool,
 }
 
 impl PlateSimulator {
-    pub fn new(width: u32, height: u32, seed: u64) -> Self {
+    pub fn new(width: u32, height: u32, world_scale: f32, params: &GenerationParams) -> Self {
         Self {
             width,
             height,
-            rng: StdRng::seed_from_u64(seed),
-            noise: Perlin::new(seed as u32),
+            seed: params.seed,
+            noise: Arc::new(PerlinNoise::new(params.seed as u32)),
+            world_scale,
+            plate_speed: params.plate_speed,
+            continental_fraction: params.continental_fraction.clamp(0.0, 1.0),
+            oceanic_flip_chance: params.oceanic_flip_chance.clamp(0.0, 1.0),
+            boundary_width: params.boundary_width,
+            continent_spread: params.continent_spread.max(0.0),
+            continent_jitter: params.continent_jitter.max(0.0),
+            elevation_exponent: if params.elevation_exponent > 0.0 { params.elevation_exponent } else { 1.0 },
+            warp_strength: params.warp_strength.max(0.0),
+            boundary_roughness: params.boundary_roughness.max(0.0),
+            pinned_plates: params.pinned_plates.clone().unwrap_or_default(),
+            top_up_plates: params.top_up_plates,
+        }
+    }
+    
+    /// Replace the Perlin noise, e.g. with a fixed field for testing.
+    pub fn set_noise(&mut self, noise: Arc<dyn ElevationNoise>) {
+        self.noise = noise;
+    }
+    
+    fn world_size(&self) -> (f32, f32) {
+        (self.width as f32 * self.world_scale, self.height as f32 * self.world_scale)
+    }
+    
+    fn world_coords(&self, x: u32, y: u32) -> (f64, f64) {
+        (x as f64 * self.world_scale as f64, y as f64 * self.world_scale as f64)
+    }
+    
+    /// Pinned plates alone, unless topping up, or with none pinned a random 6 to 9.
+    fn plate_count(&self) -> usize {
+        let mut rng = seed::derive_rng(self.seed, seed::STREAM_PLATE_COUNT);
+        let random_count = 6 + rng.gen_range(0..4);
+        if self.pinned_plates.is_empty() {
+            random_count
+        } else if self.top_up_plates {
+            random_count.max(self.pinned_plates.len())
+        } else {
+            self.pinned_plates.len()
         }
     }
     
-    pub fn simulate(&mut self, cells: &mut Vec<Vec<TerrainCell>>) -> Vec<TectonicPlate> {
-        let plate_count = 6 + self.rng.gen_range(0..4);
-        let mut plates = self.generate_plates(plate_count);
+    pub fn simulate(&self, cells: &mut [Vec<TerrainCell>]) -> Vec<TectonicPlate> {
+        let mut plates = self.generate_plates(self.plate_count());
         
         self.assign_plate_ownership(cells, &plates);
-        self.simulate_plate_interactions(cells, &mut plates);
-        self.generate_base_elevation(cells);
-        self.add_mountain_ranges(cells, &plates);
+        let mut elevation = self.generate_base_elevation();
+        self.apply_elevation_exponent(&mut elevation);
+        self.add_mountain_ranges(cells, &mut elevation, &plates);
+        Self::store_elevation(cells, &elevation);
+        
+        // Report centers in cell coordinates of this grid
+        for plate in &mut plates {
+            plate.center = (plate.center.0 / self.world_scale, plate.center.1 / self.world_scale);
+        }
         
         plates
     }
     
-    fn generate_plates(&mut self, count: usize) -> Vec<TectonicPlate> {
+    /// Move the plates along their velocities over `frames` steps, rebuilding
+    /// ownership, elevation and boundary mountains each step. Returns each
+    /// frame's elevation, row-major. Over the whole run the fastest plates
+    /// travel about a quarter of the world width.
+    pub fn drift_frames(&self, cells: &mut [Vec<TerrainCell>], frames: usize) -> Vec<Vec<f32>> {
+        let mut plates = self.generate_plates(self.plate_count());
+        let step = self.world_size().0 * 0.25 / (1.5 * self.plate_speed.max(f32::EPSILON) * frames.max(1) as f32);
+        
+        let mut result = Vec::with_capacity(frames);
+        for _ in 0..frames {
+            self.assign_plate_ownership(cells, &plates);
+            let mut elevation = self.generate_base_elevation();
+            self.apply_elevation_exponent(&mut elevation);
+            self.add_mountain_ranges(cells, &mut elevation, &plates);
+            Self::store_elevation(cells, &elevation);
+            result.push(cells.iter().flatten().map(|cell| cell.elevation).collect());
+            
+            for plate in &mut plates {
+                plate.center.0 += plate.velocity.0 * step;
+                plate.center.1 += plate.velocity.1 * step;
+            }
+        }
+        
+        result
+    }
+    
+    fn generate_plates(&self, count: usize) -> Vec<TectonicPlate> {
         let mut plates = Vec::new();
         
-        // Ensure we have some continental plates spread out
-        let continental_count = (count as f32 * 0.4).max(2.0) as usize;
+        // Ensure we have some continental plates spread out, unless none were asked for
+        let continental_count = if self.continental_fraction > 0.0 {
+            ((count as f32 * self.continental_fraction).max(2.0) as usize).min(count)
+        } else {
+            0
+        };
+        let (world_width, world_height) = self.world_size();
         
         for i in 0..count {
+            // Each plate draws from its own stream so plates can be built in any order
+            let mut rng = seed::derive_rng(self.seed, seed::STREAM_PLATES + i as u64);
+            
             let (center_x, center_y) = if i < continental_count {
                 // Spread continental plates more evenly
                 let angle = (i as f32 / continental_count as f32) * 2.0 * std::f32::consts::PI;
-                let radius = (self.width.min(self.height) as f32 * 0.3) + self.rng.gen_range(-50.0..50.0);
-                let cx = (self.width as f32 * 0.5) + radius * angle.cos();
-                let cy = (self.height as f32 * 0.5) + radius * angle.sin();
-                (cx.clamp(50.0, self.width as f32 - 50.0), 
-                 cy.clamp(50.0, self.height as f32 - 50.0))
+                let jitter = if self.continent_jitter > 0.0 {
+                    rng.gen_range(-self.continent_jitter..self.continent_jitter)
+                } else {
+                    0.0
+                };
+                let radius = (world_width.min(world_height) * self.continent_spread) + jitter;
+                let cx = (world_width * 0.5) + radius * angle.cos();
+                let cy = (world_height * 0.5) + radius * angle.sin();
+                (cx.clamp(50.0, world_width - 50.0), 
+                 cy.clamp(50.0, world_height - 50.0))
             } else {
-                (self.rng.gen_range(0.0..self.width as f32),
-                 self.rng.gen_range(0.0..self.height as f32))
+                (rng.gen_range(0.0..world_width),
+                 rng.gen_range(0.0..world_height))
             };
             
-            let velocity_x = self.rng.gen_range(-1.5..1.5);
-            let velocity_y = self.rng.gen_range(-1.5..1.5);
+            let velocity_x = rng.gen_range(-1.5..1.5) * self.plate_speed;
+            let velocity_y = rng.gen_range(-1.5..1.5) * self.plate_speed;
             
             let plate_type = if i < continental_count {
                 PlateType::Continental
             } else {
-                if self.rng.gen_bool(0.2) {
+                if rng.gen_bool(self.oceanic_flip_chance) {
                     PlateType::Continental
                 } else {
                     PlateType::Oceanic
                 }
             };
             
-            plates.push(TectonicPlate {
+            let mut plate = TectonicPlate {
                 id: i,
                 center: (center_x, center_y),
                 velocity: (velocity_x, velocity_y),
-                age: self.rng.gen_range(0.0..100.0),
+                age: rng.gen_range(0.0..100.0),
                 plate_type,
-            });
+            };
+            
+            // Authored plates keep the random draws, so top-up plates match
+            // what they would have been at the same index
+            if let Some(pin) = self.pinned_plates.get(i) {
+                plate.center = (pin.x.clamp(0.0, world_width), pin.y.clamp(0.0, world_height));
+                plate.velocity = pin.velocity.unwrap_or(plate.velocity);
+                plate.plate_type = pin.plate_type;
+            }
+            plates.push(plate);
         }
         
         plates
     }
     
-    fn assign_plate_ownership(&self, cells: &mut Vec<Vec<TerrainCell>>, plates: &[TectonicPlate]) {
-        for y in 0..self.height {
-            for x in 0..self.width {
+    fn assign_plate_ownership(&self, cells: &mut [Vec<TerrainCell>], plates: &[TectonicPlate]) {
+        let owners = crate::map_rows(self.height as usize, |y| {
+            (0..self.width).map(|x| {
                 let mut closest_plate = 0;
                 let mut min_distance = f32::INFINITY;
+                let (wx, wy) = self.boundary_coords(x, y as u32);
                 
                 for plate in plates {
-                    let dx = x as f32 - plate.center.0;
-                    let dy = y as f32 - plate.center.1;
+                    let dx = wx as f32 - plate.center.0;
+                    let dy = wy as f32 - plate.center.1;
                     let distance = (dx * dx + dy * dy).sqrt();
                     
                     if distance < min_distance {
@@ -94,116 +227,289 @@ impl PlateSimulator {
                     }
                 }
                 
-                cells[y as usize][x as usize].plate_id = closest_plate;
+                closest_plate
+            }).collect::<Vec<_>>()
+        });
+        
+        for (row, owner_row) in cells.iter_mut().zip(owners) {
+            for (cell, owner) in row.iter_mut().zip(owner_row) {
+                cell.plate_id = owner;
             }
         }
     }
     
-    fn simulate_plate_interactions(&self, cells: &mut Vec<Vec<TerrainCell>>, plates: &mut [TectonicPlate]) {
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let current_plate = cells[y as usize][x as usize].plate_id;
-                
-                let neighbors = [
-                    cells[(y - 1) as usize][x as usize].plate_id,
-                    cells[(y + 1) as usize][x as usize].plate_id,
-                    cells[y as usize][(x - 1) as usize].plate_id,
-                    cells[y as usize][(x + 1) as usize].plate_id,
-                ];
-                
-                for &neighbor_plate in &neighbors {
-                    if neighbor_plate != current_plate {
-                        let interaction_strength = self.calculate_interaction_strength(
-                            &plates[current_plate], 
-                            &plates[neighbor_plate]
-                        );
-                        
-                        cells[y as usize][x as usize].elevation += interaction_strength;
-                    }
-                }
+    /// World position a cell is judged from when picking its plate: its own,
+    /// nudged by coarse and fine noise up to `boundary_roughness` world units
+    /// so the Voronoi edges between plates wander instead of running straight.
+    fn boundary_coords(&self, x: u32, y: u32) -> (f64, f64) {
+        // World units over which the coarse and fine nudges vary
+        const COARSE_SCALE: f64 = 40.0;
+        const FINE_SCALE: f64 = 10.0;
+        let (wx, wy) = self.world_coords(x, y);
+        if self.boundary_roughness == 0.0 {
+            return (wx, wy);
+        }
+        
+        let nudge = |octave: u32| {
+            let coarse = self.noise.sample(wx / COARSE_SCALE, wy / COARSE_SCALE, octave);
+            let fine = self.noise.sample(wx / FINE_SCALE, wy / FINE_SCALE, octave);
+            (coarse + fine * 0.5) / 1.5 * self.boundary_roughness as f64
+        };
+        (wx + nudge(7), wy + nudge(8))
+    }
+    
+    /// Round the accumulated elevation into the cells.
+    #[allow(clippy::unnecessary_cast)]
+    fn store_elevation(cells: &mut [Vec<TerrainCell>], elevation: &[Vec<Elevation>]) {
+        for (row, elevation_row) in cells.iter_mut().zip(elevation) {
+            for (cell, &value) in row.iter_mut().zip(elevation_row) {
+                cell.elevation = value as f32;
             }
         }
     }
     
-    fn calculate_interaction_strength(&self, plate1: &TectonicPlate, plate2: &TectonicPlate) -> f32 {
-        let vel_diff_x = plate1.velocity.0 - plate2.velocity.0;
-        let vel_diff_y = plate1.velocity.1 - plate2.velocity.1;
-        let relative_velocity = (vel_diff_x * vel_diff_x + vel_diff_y * vel_diff_y).sqrt();
-        
-        match (plate1.plate_type, plate2.plate_type) {
-            (PlateType::Continental, PlateType::Continental) => relative_velocity * 0.8,
-            (PlateType::Continental, PlateType::Oceanic) => relative_velocity * 1.2,
-            (PlateType::Oceanic, PlateType::Continental) => relative_velocity * 1.2,
-            (PlateType::Oceanic, PlateType::Oceanic) => relative_velocity * 0.4,
+    fn generate_base_elevation(&self) -> Vec<Vec<Elevation>> {
+        if self.warp_strength > 0.0 {
+            return self.generate_warped_base_elevation();
         }
+        let xs: Vec<f64> = (0..self.width).map(|x| self.world_coords(x, 0).0).collect();
+        
+        crate::map_rows(self.height as usize, |y| {
+            let (_, wy) = self.world_coords(0, y as u32);
+            
+            // Multi-octave noise for more detailed terrain
+            let mut combined_noise: Vec<Elevation> = vec![0.0; self.width as usize];
+            self.noise.add_row(&mut combined_noise, &xs, 200.0, wy / 200.0, 1.0);
+            self.noise.add_row(&mut combined_noise, &xs, 100.0, wy / 100.0, 0.5);
+            self.noise.add_row(&mut combined_noise, &xs, 50.0, wy / 50.0, 0.25);
+            
+            combined_noise.iter().map(|&combined| (combined * 0.3 + 0.4).max(0.0)).collect()
+        })
     }
     
-    fn generate_base_elevation(&self, cells: &mut Vec<Vec<TerrainCell>>) {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                // Multi-octave noise for more detailed terrain
-                let large_features = self.noise.get([x as f64 / 200.0, y as f64 / 200.0]) as f32;
-                let medium_features = self.noise.get([x as f64 / 100.0, y as f64 / 100.0]) as f32 * 0.5;
-                let small_features = self.noise.get([x as f64 / 50.0, y as f64 / 50.0]) as f32 * 0.25;
+    /// Base elevation sampled at coordinates pushed around by two further
+    /// noise fields, which bends the soft Perlin blobs into swirled coasts
+    /// and ranges. Samples no longer share a row, so this goes cell by cell.
+    fn generate_warped_base_elevation(&self) -> Vec<Vec<Elevation>> {
+        // World units over which the warp offset varies
+        const WARP_SCALE: f64 = 150.0;
+        let strength = self.warp_strength as f64;
+        
+        crate::map_rows(self.height as usize, |y| {
+            (0..self.width).map(|x| {
+                let (wx, wy) = self.world_coords(x, y as u32);
+                let wx = wx + self.noise.sample(wx / WARP_SCALE, wy / WARP_SCALE, 5) * strength;
+                let wy = wy + self.noise.sample(wx / WARP_SCALE, wy / WARP_SCALE, 6) * strength;
                 
-                let combined_noise = large_features + medium_features + small_features;
-                let base_elevation = (combined_noise * 0.3 + 0.4).max(0.0);
+                let large_features = self.noise.sample(wx / 200.0, wy / 200.0, 0) as Elevation;
+                let medium_features = self.noise.sample(wx / 100.0, wy / 100.0, 0) as Elevation * 0.5;
+                let small_features = self.noise.sample(wx / 50.0, wy / 50.0, 0) as Elevation * 0.25;
                 
-                cells[y as usize][x as usize].elevation = base_elevation;
-            }
+                let combined_noise = large_features + medium_features + small_features;
+                (combined_noise * 0.3 + 0.4).max(0.0)
+            }).collect()
+        })
+    }
+    
+    /// Power curve on base elevation normalized over its own range: exponents
+    /// above 1 flatten lowlands and sharpen highlands, below 1 the reverse.
+    fn apply_elevation_exponent(&self, elevation: &mut [Vec<Elevation>]) {
+        if self.elevation_exponent == 1.0 {
+            return;
+        }
+        
+        let (low, high) = elevation.iter().flatten()
+            .fold((Elevation::INFINITY, Elevation::NEG_INFINITY), |(lo, hi), &e| (lo.min(e), hi.max(e)));
+        let range = high - low;
+        if range <= 0.0 {
+            return;
+        }
+        
+        let exponent = Elevation::from(self.elevation_exponent);
+        for value in elevation.iter_mut().flatten() {
+            *value = low + ((*value - low) / range).powf(exponent) * range;
         }
     }
     
-    fn add_mountain_ranges(&self, cells: &mut Vec<Vec<TerrainCell>>, plates: &[TectonicPlate]) {
-        // First pass: identify plate boundaries and add mountains there
+    fn add_mountain_ranges(&self, cells: &[Vec<TerrainCell>], elevation: &mut [Vec<Elevation>], plates: &[TectonicPlate]) {
+        // First pass: identify plate boundaries and the uplift or rifting there
+        let mut boundary = vec![vec![0.0f32; self.width as usize]; self.height as usize];
         for y in 1..self.height - 1 {
             for x in 1..self.width - 1 {
                 let current_plate = cells[y as usize][x as usize].plate_id;
                 let current_plate_type = plates[current_plate].plate_type;
                 
-                // Check if we're at a plate boundary
-                let neighbors = [
-                    cells[(y - 1) as usize][x as usize].plate_id,
-                    cells[(y + 1) as usize][x as usize].plate_id,
-                    cells[y as usize][(x - 1) as usize].plate_id,
-                    cells[y as usize][(x + 1) as usize].plate_id,
-                ];
-                
-                let is_boundary = neighbors.iter().any(|&neighbor_plate| {
-                    neighbor_plate != current_plate && 
-                    matches!((current_plate_type, plates[neighbor_plate].plate_type),
-                        (PlateType::Continental, PlateType::Continental) |
-                        (PlateType::Continental, PlateType::Oceanic) |
-                        (PlateType::Oceanic, PlateType::Continental))
-                });
+                let Some(convergence) = self.boundary_convergence(cells, plates, x, y) else { continue };
+                let neighbors = Self::edge_neighbors(cells, x, y);
                 
-                if is_boundary {
-                    // Add mountains at plate boundaries
-                    let mountain_strength = self.noise.get([
-                        x as f64 / 30.0,
-                        y as f64 / 30.0,
-                        2.0,
-                    ]) as f32;
+                let (wx, wy) = self.world_coords(x, y);
+                if convergence > 0.0 {
+                    // Convergent: plates collide and push up mountains. Two
+                    // oceanic plates subduct quietly instead.
+                    let builds_mountains = neighbors.iter().any(|&(_, _, neighbor_plate)| {
+                        neighbor_plate != current_plate &&
+                        !matches!((current_plate_type, plates[neighbor_plate].plate_type),
+                            (PlateType::Oceanic, PlateType::Oceanic))
+                    });
                     
-                    if mountain_strength > 0.1 {
-                        let elevation_boost = (mountain_strength - 0.1) * 1.5;
-                        cells[y as usize][x as usize].elevation += elevation_boost;
+                    let mountain_strength = self.noise.sample(wx / 30.0, wy / 30.0, 2) as f32;
+                    
+                    if builds_mountains && mountain_strength > 0.1 {
+                        boundary[y as usize][x as usize] = (mountain_strength - 0.1) * 1.5 * convergence.min(2.5 * self.plate_speed.max(1.0));
                     }
+                } else {
+                    // Divergent: plates pull apart and open a rift valley
+                    boundary[y as usize][x as usize] = -(-convergence).min(2.0) * 0.15;
+                }
+            }
+        }
+        
+        let boundary = self.spread_boundary(boundary);
+        
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let value = &mut elevation[y as usize][x as usize];
+                let change = Elevation::from(boundary[y as usize][x as usize]);
+                if change > 0.0 {
+                    *value += change;
+                } else if change < 0.0 {
+                    *value = (*value + change).max(0.0);
                 }
                 
                 // Add some mountains within continental plates too
-                if matches!(current_plate_type, PlateType::Continental) {
-                    let inland_mountain_noise = self.noise.get([
-                        x as f64 / 80.0,
-                        y as f64 / 80.0,
-                        3.0,
-                    ]) as f32;
+                let plate_id = cells[y as usize][x as usize].plate_id;
+                if matches!(plates[plate_id].plate_type, PlateType::Continental) {
+                    let (wx, wy) = self.world_coords(x, y);
+                    let inland_mountain_noise = self.noise.sample(wx / 80.0, wy / 80.0, 3) as Elevation;
                     
                     if inland_mountain_noise > 0.4 {
-                        cells[y as usize][x as usize].elevation += (inland_mountain_noise - 0.4) * 0.8;
+                        *value += (inland_mountain_noise - 0.4) * 0.8;
+                    }
+                }
+            }
+        }
+    }
+    
+    /// Plates of the four edge neighbours of an interior cell, with offsets.
+    fn edge_neighbors(cells: &[Vec<TerrainCell>], x: u32, y: u32) -> [(i32, i32, usize); 4] {
+        [
+            (0, -1, cells[(y - 1) as usize][x as usize].plate_id),
+            (0, 1, cells[(y + 1) as usize][x as usize].plate_id),
+            (-1, 0, cells[y as usize][(x - 1) as usize].plate_id),
+            (1, 0, cells[y as usize][(x + 1) as usize].plate_id),
+        ]
+    }
+    
+    /// Mean convergence across the plate boundaries at interior cell (x, y):
+    /// positive where the plates meeting there close, negative where they open.
+    /// `None` if all four edge neighbours share the cell's plate.
+    fn boundary_convergence(&self, cells: &[Vec<TerrainCell>], plates: &[TectonicPlate], x: u32, y: u32) -> Option<f32> {
+        let current_plate = cells[y as usize][x as usize].plate_id;
+        let mut convergence = 0.0;
+        let mut boundary_count = 0;
+        
+        for (dx, dy, neighbor_plate) in Self::edge_neighbors(cells, x, y) {
+            if neighbor_plate == current_plate {
+                continue;
+            }
+            
+            convergence += convergence_speed(
+                &plates[current_plate],
+                &plates[neighbor_plate],
+                (dx as f32, dy as f32),
+            );
+            boundary_count += 1;
+        }
+        
+        (boundary_count > 0).then(|| convergence / boundary_count as f32)
+    }
+    
+    /// Give the sea floor bathymetry: mid-ocean ridges where two oceanic plates
+    /// pull apart, and low abyssal-plain noise elsewhere, scaled by `strength`.
+    /// Runs once water bodies are labelled; lakes are left alone, and cells stay
+    /// below their row's sea level so the coastline is untouched.
+    pub fn shape_ocean_floor(&self, cells: &mut [Vec<TerrainCell>], plates: &[TectonicPlate], sea_levels: &[f32], strength: f32) {
+        const RIDGE_HEIGHT: f32 = 0.1;
+        const ABYSSAL_NOISE: f32 = 0.03;
+        // Keeps reshaped floor strictly under water
+        const MIN_DEPTH: f32 = 0.001;
+        
+        if strength <= 0.0 {
+            return;
+        }
+        
+        let mut ridges = vec![vec![0.0f32; self.width as usize]; self.height as usize];
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let cell = &cells[y as usize][x as usize];
+                if !cell.is_water || !matches!(plates[cell.plate_id].plate_type, PlateType::Oceanic) {
+                    continue;
+                }
+                let oceanic_pair = Self::edge_neighbors(cells, x, y).iter()
+                    .any(|&(_, _, plate)| plate != cell.plate_id && matches!(plates[plate].plate_type, PlateType::Oceanic));
+                if let Some(convergence) = self.boundary_convergence(cells, plates, x, y) {
+                    if convergence < 0.0 && oceanic_pair {
+                        ridges[y as usize][x as usize] = (-convergence).min(2.0) * 0.5 * RIDGE_HEIGHT;
                     }
                 }
             }
         }
+        let ridges = self.spread_boundary(ridges);
+        
+        for (y, (row, &sea_level)) in cells.iter_mut().zip(sea_levels).enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !cell.is_water || cell.is_freshwater {
+                    continue;
+                }
+                let (wx, wy) = self.world_coords(x as u32, y as u32);
+                let abyssal = self.noise.sample(wx / 20.0, wy / 20.0, 4) as f32 * ABYSSAL_NOISE;
+                let shaped = cell.elevation + (ridges[y][x] + abyssal) * strength;
+                cell.elevation = shaped.min(sea_level - MIN_DEPTH).max(0.0);
+            }
+        }
     }
+    
+    /// Let each boundary cell's uplift or rift fade linearly to nothing over
+    /// `boundary_width` world cells, so ranges get foothills instead of a
+    /// one-cell wall. Where spreads overlap the strongest change wins.
+    fn spread_boundary(&self, boundary: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        let reach = self.boundary_width as f32 / self.world_scale;
+        if reach <= 1.0 {
+            return boundary;
+        }
+        
+        let radius = reach.ceil() as isize - 1;
+        let mut spread = boundary.clone();
+        for (y, row) in boundary.iter().enumerate() {
+            for (x, &change) in row.iter().enumerate() {
+                if change == 0.0 {
+                    continue;
+                }
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        let (nx, ny) = (x as isize + dx, y as isize + dy);
+                        if nx < 0 || ny < 0 || nx >= self.width as isize || ny >= self.height as isize {
+                            continue;
+                        }
+                        let falloff = 1.0 - ((dx * dx + dy * dy) as f32).sqrt() / reach;
+                        let target = &mut spread[ny as usize][nx as usize];
+                        if falloff > 0.0 && (change * falloff).abs() > target.abs() {
+                            *target = change * falloff;
+                        }
+                    }
+                }
+            }
+        }
+        spread
+    }
+}
+
+/// Closing speed of two plates across their shared boundary. `normal` points
+/// from `plate1` toward `plate2`; positive values mean the plates converge,
+/// negative values mean they diverge.
+pub(crate) fn convergence_speed(plate1: &TectonicPlate, plate2: &TectonicPlate, normal: (f32, f32)) -> f32 {
+    let vel_diff_x = plate1.velocity.0 - plate2.velocity.0;
+    let vel_diff_y = plate1.velocity.1 - plate2.velocity.1;
+    
+    vel_diff_x * normal.0 + vel_diff_y * normal.1
 }
\ No newline at end of file
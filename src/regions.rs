@@ -0,0 +1,190 @@
+use crate::{BiomeType, TerrainData};
+use crate::morphology;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// A contiguous area of land sharing one biome. Water separates regions, so
+/// neighbours are only regions reached across land.
+#[derive(Debug, Clone, Serialize)]
+pub struct Region {
+    pub id: usize,
+    pub biome: BiomeType,
+    /// Mean cell position as (x, y) in cells.
+    pub centroid: (f32, f32),
+    pub area: usize,
+    pub neighbors: Vec<usize>,
+}
+
+/// Label 8-connected same-biome land regions and their adjacency. Regions are
+/// numbered by biome in first-seen scan order, then by scan order within it.
+pub fn biome_regions(terrain: &TerrainData) -> Vec<Region> {
+    let mut biomes: Vec<BiomeType> = Vec::new();
+    for (_, _, cell) in terrain.iter_cells() {
+        if !cell.is_water && !biomes.contains(&cell.biome) {
+            biomes.push(cell.biome);
+        }
+    }
+    
+    let height = terrain.height as usize;
+    let width = terrain.width as usize;
+    let mut ids: Vec<Vec<Option<usize>>> = vec![vec![None; width]; height];
+    let mut regions: Vec<Region> = Vec::new();
+    for biome in biomes {
+        let mask: Vec<Vec<bool>> = terrain.cells.iter()
+            .map(|row| row.iter().map(|cell| !cell.is_water && cell.biome == biome).collect())
+            .collect();
+        let (labels, count) = morphology::label_components(&mask);
+        
+        let first = regions.len();
+        regions.extend((0..count).map(|i| Region {
+            id: first + i,
+            biome,
+            centroid: (0.0, 0.0),
+            area: 0,
+            neighbors: Vec::new(),
+        }));
+        for (y, row) in labels.iter().enumerate() {
+            for (x, label) in row.iter().enumerate() {
+                if let Some(label) = label {
+                    let region = &mut regions[first + label];
+                    region.area += 1;
+                    region.centroid.0 += x as f32;
+                    region.centroid.1 += y as f32;
+                    ids[y][x] = Some(first + label);
+                }
+            }
+        }
+    }
+    
+    let mut neighbors = vec![BTreeSet::new(); regions.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let Some(id) = ids[y][x] else { continue };
+            for (nx, ny) in [(x + 1, y), (x, y + 1), (x + 1, y + 1), (x.wrapping_sub(1), y + 1)] {
+                if let Some(Some(other)) = ids.get(ny).and_then(|row| row.get(nx)) {
+                    if *other != id {
+                        neighbors[id].insert(*other);
+                        neighbors[*other].insert(id);
+                    }
+                }
+            }
+        }
+    }
+    
+    for (region, adjacent) in regions.iter_mut().zip(neighbors) {
+        region.centroid.0 /= region.area as f32;
+        region.centroid.1 /= region.area as f32;
+        region.neighbors = adjacent.into_iter().collect();
+    }
+    regions
+}
+
+/// Variance of land elevation within a block below which the block counts as flat.
+const FLAT_VARIANCE: f32 = 2.5e-5;
+/// Side of the square blocks, in cells, over which flatness is measured.
+const FLAT_BLOCK: usize = 8;
+
+/// What makes a flagged region uniform.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Uniformity {
+    Biome(BiomeType),
+    FlatElevation,
+}
+
+/// A large contiguous area with one biome or almost no relief, which usually
+/// points at a parameter problem.
+#[derive(Debug, Clone, Serialize)]
+pub struct UniformRegion {
+    pub kind: Uniformity,
+    /// Mean cell position as (x, y) in cells.
+    pub centroid: (f32, f32),
+    pub area: usize,
+    /// Share of the whole map, 0-100.
+    pub percent: f32,
+}
+
+/// Contiguous single-biome land regions and flat land areas covering at least
+/// `min_percent` of the map, biome regions first, each group largest first.
+pub fn uniform_regions(terrain: &TerrainData, min_percent: f32) -> Vec<UniformRegion> {
+    let total = (terrain.width * terrain.height) as f32;
+    let min_area = (total * min_percent / 100.0).max(1.0) as usize;
+    
+    // Terrain-only worlds have no biomes, so every land region would match
+    let biomes = if terrain.generation_params.terrain_only { Vec::new() } else { biome_regions(terrain) };
+    let mut biome_regions: Vec<UniformRegion> = biomes.into_iter()
+        .filter(|region| region.area >= min_area)
+        .map(|region| UniformRegion {
+            kind: Uniformity::Biome(region.biome),
+            centroid: region.centroid,
+            area: region.area,
+            percent: region.area as f32 / total * 100.0,
+        })
+        .collect();
+    biome_regions.sort_by_key(|region| std::cmp::Reverse(region.area));
+    
+    let mut flat_regions: Vec<UniformRegion> = flat_components(terrain).into_iter()
+        .filter(|&(area, _)| area >= min_area)
+        .map(|(area, centroid)| UniformRegion {
+            kind: Uniformity::FlatElevation,
+            centroid,
+            area,
+            percent: area as f32 / total * 100.0,
+        })
+        .collect();
+    flat_regions.sort_by_key(|region| std::cmp::Reverse(region.area));
+    
+    biome_regions.extend(flat_regions);
+    biome_regions
+}
+
+/// Area and centroid of each connected patch of land lying in flat blocks.
+/// Blocks that are mostly water are skipped, since open water is meant to be flat.
+fn flat_components(terrain: &TerrainData) -> Vec<(usize, (f32, f32))> {
+    let height = terrain.height as usize;
+    let width = terrain.width as usize;
+    let mut mask = vec![vec![false; width]; height];
+    
+    for block_y in (0..height).step_by(FLAT_BLOCK) {
+        for block_x in (0..width).step_by(FLAT_BLOCK) {
+            let ys = block_y..(block_y + FLAT_BLOCK).min(height);
+            let xs = block_x..(block_x + FLAT_BLOCK).min(width);
+            let land: Vec<f32> = ys.clone()
+                .flat_map(|y| xs.clone().map(move |x| (x, y)))
+                .map(|(x, y)| &terrain.cells[y][x])
+                .filter(|cell| !cell.is_water)
+                .map(|cell| cell.elevation)
+                .collect();
+            if land.len() * 2 < ys.len() * xs.len() {
+                continue;
+            }
+            
+            let mean = land.iter().sum::<f32>() / land.len() as f32;
+            let variance = land.iter().map(|e| (e - mean) * (e - mean)).sum::<f32>() / land.len() as f32;
+            if variance < FLAT_VARIANCE {
+                for y in ys.clone() {
+                    for x in xs.clone() {
+                        mask[y][x] = !terrain.cells[y][x].is_water;
+                    }
+                }
+            }
+        }
+    }
+    
+    let (labels, count) = morphology::label_components(&mask);
+    let mut components = vec![(0usize, (0.0f32, 0.0f32)); count];
+    for (y, row) in labels.iter().enumerate() {
+        for (x, label) in row.iter().enumerate() {
+            if let Some(label) = label {
+                let (area, centroid) = &mut components[*label];
+                *area += 1;
+                centroid.0 += x as f32;
+                centroid.1 += y as f32;
+            }
+        }
+    }
+    for (area, centroid) in &mut components {
+        centroid.0 /= *area as f32;
+        centroid.1 /= *area as f32;
+    }
+    components
+}
\ No newline at end of file
@@ -1,20 +1,280 @@
-use crate::TerrainCell;
+use crate::{TerrainCell, BiomeType, GenerationParams, Projection, RiverConnectivity, Topology};
+use crate::seed;
+use serde::Serialize;
+
+/// Upstream cells a channel needs before it shows as a river.
+const MIN_RIVER_ACCUMULATION: u32 = 3;
+
+/// Rise per cell away from the outlet on a depression-filled surface.
+const FILL_STEP: f32 = 1e-4;
+
+/// Degrees below freezing at which a cell holds a full snowpack.
+const SNOWPACK_RANGE: f32 = 10.0;
+
+/// One cell of a traced river and the cell it drains into, if any.
+struct RiverStep {
+    x: usize,
+    y: usize,
+    next: Option<(usize, usize)>,
+}
+
+/// River cells as a directed graph, each linked to the cell it drains into.
+/// Cells are indexed row-major.
+pub struct RiverNetwork {
+    width: usize,
+    is_river: Vec<bool>,
+    downstream: Vec<Option<usize>>,
+}
+
+impl RiverNetwork {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            is_river: vec![false; width * height],
+            downstream: vec![None; width * height],
+        }
+    }
+    
+    /// Add a river cell. A cell already on the network keeps its first link.
+    fn add(&mut self, x: usize, y: usize, next: Option<(usize, usize)>) {
+        let index = y * self.width + x;
+        if !self.is_river[index] {
+            self.is_river[index] = true;
+            self.downstream[index] = next.map(|(nx, ny)| ny * self.width + nx);
+        }
+    }
+    
+    /// River cell that (x, y) drains into, or `None` where the river ends or
+    /// (x, y) is not on the network.
+    pub fn next(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let index = y * self.width + x;
+        if !self.is_river[index] {
+            return None;
+        }
+        self.river_downstream(index).map(|next| (next % self.width, next / self.width))
+    }
+    
+    /// Downstream river cell, ignoring links that leave the network.
+    fn river_downstream(&self, index: usize) -> Option<usize> {
+        self.downstream[index].filter(|&next| self.is_river[next])
+    }
+    
+    /// Strahler stream order of every cell, 0 off the network. Headwaters are
+    /// order 1, and the order rises by one only where two streams of the
+    /// current highest order meet.
+    pub fn strahler_orders(&self) -> Vec<u8> {
+        let count = self.is_river.len();
+        let mut upstream = vec![0usize; count];
+        for index in 0..count {
+            if self.is_river[index] {
+                if let Some(next) = self.river_downstream(index) {
+                    upstream[next] += 1;
+                }
+            }
+        }
+        
+        // Highest incoming order and how many tributaries carry it
+        let mut highest = vec![0u8; count];
+        let mut highest_count = vec![0usize; count];
+        let mut orders = vec![0u8; count];
+        let mut ready: Vec<usize> = (0..count).filter(|&i| self.is_river[i] && upstream[i] == 0).collect();
+        
+        while let Some(index) = ready.pop() {
+            orders[index] = match (highest[index], highest_count[index]) {
+                (0, _) => 1,
+                (order, n) if n >= 2 => order.saturating_add(1),
+                (order, _) => order,
+            };
+            
+            if let Some(next) = self.river_downstream(index) {
+                if orders[index] > highest[next] {
+                    highest[next] = orders[index];
+                    highest_count[next] = 1;
+                } else if orders[index] == highest[next] {
+                    highest_count[next] += 1;
+                }
+                upstream[next] -= 1;
+                if upstream[next] == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+        
+        // Cells left on a loop never became ready; give them what reached them
+        for index in 0..count {
+            if self.is_river[index] && orders[index] == 0 {
+                orders[index] = highest[index].max(1);
+            }
+        }
+        
+        orders
+    }
+}
+
+/// Counts from one `generate_rivers` run. Sources are kept or dropped by
+/// `max_rivers`; a stuck river ends in a pit on land instead of reaching water.
+/// The longest river is measured in cells from its source to where it ends,
+/// including any stretch shared with rivers it joins.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RiverStats {
+    pub sources_found: usize,
+    pub sources_kept: usize,
+    pub rivers: usize,
+    pub stuck: usize,
+    pub longest_river: usize,
+    pub longest_river_source: Option<(usize, usize)>,
+}
+
+/// A traced river and whether it reached an outlet.
+struct TracedRiver {
+    steps: Vec<RiverStep>,
+    reached_outlet: bool,
+}
+
+/// Priority-flood queue entry, ordered so the lowest cell pops first.
+#[derive(PartialEq)]
+struct FloodCell {
+    elevation: f32,
+    x: usize,
+    y: usize,
+}
+
+impl Eq for FloodCell {}
+
+impl Ord for FloodCell {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.elevation.total_cmp(&self.elevation)
+            .then_with(|| (other.y, other.x).cmp(&(self.y, self.x)))
+    }
+}
+
+impl PartialOrd for FloodCell {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
 pub struct RiverGenerator {
     width: u32,
     height: u32,
+    projection: Projection,
+    topology: Topology,
+    min_river_length: usize,
+    connectivity: RiverConnectivity,
+    max_rivers: usize,
+    require_outlet: bool,
+    snowmelt: f32,
+    meander_seed: u64,
 }
 
 impl RiverGenerator {
-    pub fn new(width: u32, height: u32) -> Self {
-        Self { width, height }
+    pub fn new(width: u32, height: u32, params: &GenerationParams) -> Self {
+        Self {
+            width,
+            height,
+            projection: params.projection,
+            topology: params.topology,
+            min_river_length: params.min_river_length,
+            connectivity: params.river_connectivity,
+            max_rivers: params.max_rivers,
+            require_outlet: params.require_outlet,
+            snowmelt: params.snowmelt,
+            meander_seed: seed::derive_seed(params.seed, seed::STREAM_RIVER_MEANDER),
+        }
     }
     
-    pub fn generate_rivers(&self, cells: &mut Vec<Vec<TerrainCell>>) {
-        let sources = self.find_river_sources(cells);
+    pub fn generate_rivers(&self, cells: &mut [Vec<TerrainCell>]) -> (RiverNetwork, RiverStats) {
+        if self.require_outlet {
+            self.fill_depressions(cells);
+        }
+        let receivers = self.accumulate_flow(cells);
+        let mut sources = self.find_river_sources(cells);
+        let mut stats = RiverStats { sources_found: sources.len(), ..RiverStats::default() };
+        if self.max_rivers > 0 && sources.len() > self.max_rivers {
+            sources = self.prioritize_sources(sources, cells);
+        }
+        stats.sources_kept = sources.len();
+        let mut network = RiverNetwork::new(self.width as usize, self.height as usize);
         
         for source in sources {
-            self.trace_river(source.0, source.1, cells);
+            let river = self.trace_river(source.0, source.1, cells, &receivers);
+            
+            // Short stubs are dropped before they mark or carve anything
+            if river.steps.len() >= self.min_river_length {
+                self.commit_river(&river.steps, cells, &mut network);
+                stats.rivers += 1;
+                if !river.reached_outlet {
+                    stats.stuck += 1;
+                }
+                if river.steps.len() > stats.longest_river {
+                    stats.longest_river = river.steps.len();
+                    stats.longest_river_source = Some(source);
+                }
+            }
+        }
+        
+        let orders = network.strahler_orders();
+        for (cell, order) in cells.iter_mut().flatten().zip(orders) {
+            cell.river_order = order;
+        }
+        
+        (network, stats)
+    }
+    
+    /// Priority-flood depression filling: raise every land pit to just above
+    /// its spill point so each land cell has a strictly lower neighbour on a
+    /// path to open water or a pole.
+    fn fill_depressions(&self, cells: &mut [Vec<TerrainCell>]) {
+        let width = self.width as usize;
+        let mut queue = std::collections::BinaryHeap::new();
+        let mut done = vec![false; width * self.height as usize];
+        for (y, row) in cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let open_water = cell.is_water && cell.biome != BiomeType::SeaIce;
+                if open_water || self.is_pole(y) {
+                    done[y * width + x] = true;
+                    queue.push(FloodCell { elevation: cell.elevation, x, y });
+                }
+            }
+        }
+        
+        while let Some(FloodCell { elevation, x, y }) = queue.pop() {
+            for (nx, ny, _) in self.flow_neighbors(x, y) {
+                if done[ny * width + nx] {
+                    continue;
+                }
+                done[ny * width + nx] = true;
+                let neighbor = &mut cells[ny][nx];
+                if !neighbor.is_water {
+                    neighbor.elevation = neighbor.elevation.max(elevation + FILL_STEP);
+                }
+                queue.push(FloodCell { elevation: neighbor.elevation, x: nx, y: ny });
+            }
+        }
+    }
+    
+    /// Keep the `max_rivers` most prominent sources, standing highest above
+    /// their neighbours, in their original scan order so tracing is unchanged
+    /// apart from the dropped rivers.
+    fn prioritize_sources(&self, sources: Vec<(usize, usize)>, cells: &[Vec<TerrainCell>]) -> Vec<(usize, usize)> {
+        let mut ranked: Vec<(usize, f32)> = sources.iter().enumerate()
+            .map(|(i, &(x, y))| (i, cells[y][x].elevation - self.get_average_neighbor_elevation(x, y, cells)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        ranked.truncate(self.max_rivers);
+        ranked.sort_by_key(|&(i, _)| i);
+        ranked.into_iter().map(|(i, _)| sources[i]).collect()
+    }
+    
+    fn commit_river(&self, river: &[RiverStep], cells: &mut [Vec<TerrainCell>], network: &mut RiverNetwork) {
+        for step in river {
+            // Don't override biome - let the visualization handle it
+            cells[step.y][step.x].has_river = true;
+            network.add(step.x, step.y, step.next);
+            if let Some((next_x, next_y)) = step.next {
+                let flow = cells[step.y][step.x].flow_accumulation as f32;
+                self.carve_channel(step.x, step.y, next_x, next_y, flow, cells);
+            }
         }
     }
     
@@ -25,8 +285,8 @@ impl RiverGenerator {
             for x in 1..self.width as usize - 1 {
                 let cell = &cells[y][x];
                 
-                // Rivers start in mountains with high rainfall
-                if !cell.is_water && cell.elevation > 1.0 && cell.rainfall > 6.0 {
+                // Rivers start in mountains with high rainfall or a deep snowpack
+                if !cell.is_water && cell.elevation > 1.0 && self.water_supply(cell) > 6.0 {
                     // Check if this is a good watershed point (high elevation relative to surroundings)
                     let avg_neighbor_elevation = self.get_average_neighbor_elevation(x, y, cells);
                     
@@ -40,33 +300,87 @@ impl RiverGenerator {
         sources
     }
     
+    /// Rainfall plus meltwater from permanent snow and ice, which lets cold
+    /// dry ranges feed rivers down onto arid plains.
+    fn water_supply(&self, cell: &TerrainCell) -> f32 {
+        let snowpack = match cell.biome {
+            BiomeType::Glacier => 1.0,
+            _ => (-cell.temperature / SNOWPACK_RANGE).clamp(0.0, 1.0),
+        };
+        cell.rainfall + self.snowmelt * snowpack
+    }
+    
+    /// D8 flow accumulation: every land cell drains to its steepest downhill
+    /// neighbour, and each cell counts itself plus all cells upstream of it.
+    /// Cells are visited from highest to lowest, so every donor is counted
+    /// before its receiver passes the total on, and each cell's own total is
+    /// final when its receiver is chosen. Returns the receivers, which rivers
+    /// then follow so the channels drawn are the ones that were counted.
+    fn accumulate_flow(&self, cells: &mut [Vec<TerrainCell>]) -> Vec<Vec<Option<(usize, usize)>>> {
+        let mut order = Vec::with_capacity((self.width * self.height) as usize);
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                cell.flow_accumulation = 1;
+                order.push((x, y));
+            }
+        }
+        order.sort_by(|a, b| cells[b.1][b.0].elevation.partial_cmp(&cells[a.1][a.0].elevation).unwrap());
+        
+        let mut receivers = vec![vec![None; self.width as usize]; self.height as usize];
+        for (x, y) in order {
+            receivers[y][x] = self.flow_direction(x, y, cells);
+            if let Some((nx, ny)) = receivers[y][x] {
+                cells[ny][nx].flow_accumulation += cells[y][x].flow_accumulation;
+            }
+        }
+        receivers
+    }
+    
+    /// D8 receiver of every cell as `receivers[y][x]`, chosen as flow
+    /// accumulation chooses them but from the cells' current elevation.
+    /// `None` on water and poles, and on land pits with no lower neighbour.
+    pub fn flow_directions(&self, cells: &[Vec<TerrainCell>]) -> Vec<Vec<Option<(usize, usize)>>> {
+        (0..cells.len()).map(|y| {
+            (0..cells[y].len()).map(|x| self.flow_direction(x, y, cells)).collect()
+        }).collect()
+    }
+    
+    /// D8 receiver of a cell, or `None` where water leaves the land surface.
+    /// Larger flows meander, so the cell's accumulation must already be final.
+    fn flow_direction(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> Option<(usize, usize)> {
+        if cells[y][x].is_water || self.is_pole(y) {
+            return None;
+        }
+        self.find_best_flow_direction(x, y, cells, cells[y][x].flow_accumulation as f32)
+    }
+    
+    fn is_pole(&self, y: usize) -> bool {
+        self.projection == Projection::Equirectangular && (y == 0 || y == self.height as usize - 1)
+    }
+    
     fn get_average_neighbor_elevation(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> f32 {
         let mut total = 0.0;
         let mut count = 0;
         
-        for dy in -1i32..=1 {
-            for dx in -1i32..=1 {
-                if dx == 0 && dy == 0 { continue; }
-                
-                let nx = x as i32 + dx;
-                let ny = y as i32 + dy;
-                
-                if nx >= 0 && nx < self.width as i32 && ny >= 0 && ny < self.height as i32 {
-                    total += cells[ny as usize][nx as usize].elevation;
-                    count += 1;
-                }
-            }
+        for (nx, ny, _) in self.neighbors(x, y) {
+            total += cells[ny][nx].elevation;
+            count += 1;
         }
         
         total / count as f32
     }
     
     
-    fn trace_river(&self, start_x: usize, start_y: usize, cells: &mut Vec<Vec<TerrainCell>>) {
+    /// Follow the accumulation receivers downhill from a source, returning the
+    /// cells that carry enough upstream area to count as river and whether the
+    /// flow ended in water or at a pole rather than in a pit.
+    fn trace_river(&self, start_x: usize, start_y: usize, cells: &[Vec<TerrainCell>],
+                   receivers: &[Vec<Option<(usize, usize)>>]) -> TracedRiver {
         let mut current_x = start_x;
         let mut current_y = start_y;
         let mut visited = std::collections::HashSet::new();
-        let mut flow_volume = 1.0; // Start with small flow
+        let mut river = Vec::new();
+        let mut reached_outlet = false;
         
         loop {
             if visited.contains(&(current_x, current_y)) {
@@ -76,52 +390,59 @@ impl RiverGenerator {
             visited.insert((current_x, current_y));
             
             if cells[current_y][current_x].is_water {
+                reached_outlet = true;
                 break;
             }
             
-            // Only mark as river if flow is significant enough
-            if flow_volume > 0.3 {
-                cells[current_y][current_x].has_river = true;
-                // Don't override biome - let the visualization handle it
-            }
+            // A river that reaches a pole has arrived at a single point and cannot
+            // run along the pole row as if it were an edge
+            let flow = cells[current_y][current_x].flow_accumulation;
+            let next = if self.is_pole(current_y) {
+                reached_outlet = true;
+                None
+            } else {
+                receivers[current_y][current_x]
+            };
             
-            // Add flow from local rainfall and nearby rivers
-            flow_volume += cells[current_y][current_x].rainfall * 0.1;
-            flow_volume += self.count_tributary_flow(current_x, current_y, cells) * 0.2;
+            // Only a river once enough upstream area drains through
+            if flow >= MIN_RIVER_ACCUMULATION {
+                river.push(RiverStep { x: current_x, y: current_y, next });
+            }
             
-            if let Some((next_x, next_y)) = self.find_best_flow_direction(current_x, current_y, cells, flow_volume) {
-                current_x = next_x;
-                current_y = next_y;
-            } else {
-                break;
+            match next {
+                Some((next_x, next_y)) => {
+                    current_x = next_x;
+                    current_y = next_y;
+                }
+                None => break,
             }
             
             if visited.len() > 2000 {
                 break;
             }
         }
+        
+        TracedRiver { steps: river, reached_outlet }
     }
     
-    fn count_tributary_flow(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>]) -> f32 {
-        let mut flow = 0.0;
+    /// Lower a river cell by the log of its flow so rivers sit in valleys, the
+    /// largest trunks deepest. Carving is capped per cell and never cuts below
+    /// the downstream cell, so the channel keeps draining and cannot deepen
+    /// into a canyon.
+    fn carve_channel(&self, x: usize, y: usize, next_x: usize, next_y: usize,
+                     flow_volume: f32, cells: &mut [Vec<TerrainCell>]) {
+        // Depth per factor of e in upstream area
+        const CARVE_RATE: f32 = 0.01;
+        const MAX_CARVE_DEPTH: f32 = 0.1;
         
-        for dy in -1i32..=1 {
-            for dx in -1i32..=1 {
-                if dx == 0 && dy == 0 { continue; }
-                
-                let nx = x as i32 + dx;
-                let ny = y as i32 + dy;
-                
-                if nx >= 0 && nx < self.width as i32 && ny >= 0 && ny < self.height as i32 {
-                    let neighbor = &cells[ny as usize][nx as usize];
-                    if neighbor.has_river && neighbor.elevation > cells[y][x].elevation {
-                        flow += 1.0;
-                    }
-                }
-            }
+        let depth = (flow_volume.max(1.0).ln() * CARVE_RATE).min(MAX_CARVE_DEPTH);
+        let mut floor = cells[next_y][next_x].elevation;
+        if self.require_outlet {
+            // Stay strictly above the next cell so later rivers still drain through
+            floor += FILL_STEP;
         }
-        
-        flow
+        let cell = &mut cells[y][x];
+        cell.elevation = (cell.elevation - depth).max(floor).min(cell.elevation);
     }
     
     fn find_best_flow_direction(&self, x: usize, y: usize, cells: &[Vec<TerrainCell>], flow_volume: f32) -> Option<(usize, usize)> {
@@ -129,41 +450,32 @@ impl RiverGenerator {
         let mut best_pos = None;
         let current_elevation = cells[y][x].elevation;
         
-        for dy in -1i32..=1 {
-            for dx in -1i32..=1 {
-                if dx == 0 && dy == 0 { continue; }
+        for (nx, ny, distance) in self.flow_neighbors(x, y) {
+            let neighbor = &cells[ny][nx];
+            let neighbor_elevation = neighbor.elevation;
+            
+            // Frozen coast has no open water for a river mouth
+            if neighbor.biome == BiomeType::SeaIce {
+                continue;
+            }
+            
+            if neighbor_elevation < current_elevation {
+                // Calculate flow preference based on elevation drop and some randomness for meandering
+                let elevation_drop = current_elevation - neighbor_elevation;
                 
-                let nx = x as i32 + dx;
-                let ny = y as i32 + dy;
+                // Add some random meandering for larger rivers
+                let meander_factor = if flow_volume > 2.0 {
+                    (self.meander_noise(x, y, nx, ny) - 0.5) * 0.3 // Random factor between -0.15 and 0.15
+                } else {
+                    0.0
+                };
                 
-                if nx >= 0 && nx < self.width as i32 && ny >= 0 && ny < self.height as i32 {
-                    let neighbor_elevation = cells[ny as usize][nx as usize].elevation;
-                    
-                    if neighbor_elevation < current_elevation {
-                        // Calculate flow preference based on elevation drop and some randomness for meandering
-                        let elevation_drop = current_elevation - neighbor_elevation;
-                        let distance = ((dx * dx + dy * dy) as f32).sqrt(); // Diagonal penalty
-                        
-                        // Add some random meandering for larger rivers
-                        let meander_factor = if flow_volume > 2.0 {
-                            use std::collections::hash_map::DefaultHasher;
-                            use std::hash::{Hash, Hasher};
-                            
-                            let mut hasher = DefaultHasher::new();
-                            (x, y, nx, ny).hash(&mut hasher);
-                            let hash_val = hasher.finish() as f32 / u64::MAX as f32;
-                            (hash_val - 0.5) * 0.3 // Random factor between -0.15 and 0.15
-                        } else {
-                            0.0
-                        };
-                        
-                        let score = distance / (elevation_drop + 0.1) - meander_factor;
-                        
-                        if score < best_score {
-                            best_score = score;
-                            best_pos = Some((nx as usize, ny as usize));
-                        }
-                    }
+                // Distance gives diagonal moves on the square grid a penalty
+                let score = distance / (elevation_drop + 0.1) - meander_factor;
+                
+                if score < best_score {
+                    best_score = score;
+                    best_pos = Some((nx, ny));
                 }
             }
         }
@@ -171,4 +483,26 @@ impl RiverGenerator {
         best_pos
     }
     
+    /// Uniform value in [0, 1) for the step from (x, y) to (nx, ny), fixed by
+    /// the world seed and the two cells alone, so tracing order and platform
+    /// never change it.
+    fn meander_noise(&self, x: usize, y: usize, nx: usize, ny: usize) -> f32 {
+        let width = self.width as u64;
+        let from = y as u64 * width + x as u64;
+        let to = ny as u64 * width + nx as u64;
+        let hash = seed::splitmix64(self.meander_seed ^ seed::splitmix64(from << 32 | to));
+        (hash >> 40) as f32 / (1u64 << 24) as f32
+    }
+    
+    fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize, f32)> {
+        self.topology.neighbors(x, y, self.width as usize, self.height as usize)
+    }
+    
+    /// Neighbours water may flow to, which drops the diagonals under
+    /// four-connectivity on the square grid.
+    fn flow_neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize, f32)> {
+        let edges_only = self.connectivity == RiverConnectivity::Four && self.topology == Topology::Square;
+        self.neighbors(x, y).filter(move |&(_, _, distance)| !edges_only || distance == 1.0)
+    }
+    
 }
\ No newline at end of file
@@ -0,0 +1,40 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+// Stream identifiers keep each consumer of randomness independent of the
+// others, so adding draws in one stage never shifts the values seen by another.
+pub const STREAM_PLATE_COUNT: u64 = 0x100;
+pub const STREAM_PLATES: u64 = 0x1_0000;
+pub const STREAM_TEMPERATURE_NOISE: u64 = 0x200;
+pub const STREAM_RAINFALL_NOISE: u64 = 0x201;
+pub const STREAM_RESEED: u64 = 0x300;
+pub const STREAM_RIVER_MEANDER: u64 = 0x400;
+
+/// SplitMix64 finalizer: a cheap bijective mix with good avalanche behaviour.
+pub fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Derive a sub-seed for `stream` from the master seed. The result depends only
+/// on the two inputs, never on call order, so serial and parallel runs agree.
+pub fn derive_seed(master: u64, stream: u64) -> u64 {
+    splitmix64(splitmix64(master) ^ stream)
+}
+
+/// Deterministic RNG for one tile, row, plate or other unit of work.
+pub fn derive_rng(master: u64, stream: u64) -> StdRng {
+    StdRng::seed_from_u64(derive_seed(master, stream))
+}
+
+/// Seed from a command-line value: a number is used as is, anything else is
+/// hashed with 64-bit FNV-1a, which is stable across platforms and releases.
+pub fn parse_seed(text: &str) -> u64 {
+    text.parse().unwrap_or_else(|_| {
+        text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    })
+}
\ No newline at end of file
@@ -1,28 +1,452 @@
-use crate::{TerrainData, TerrainCell, BiomeType, GenerationParams};
+use crate::{TerrainData, TerrainCell, TectonicPlate, WaterBody, BiomeType, GenerationParams, Symmetry, WaterMode, percentile_of_sorted, max_slope, coast_distances};
 use crate::plate_tectonics::PlateSimulator;
+use crate::noise_source::ElevationNoise;
 use crate::climate::ClimateSimulator;
-use crate::biomes::BiomeAssigner;
-use crate::rivers::RiverGenerator;
+use crate::biomes::{AltitudeLines, BiomeAssigner};
+use crate::rivers::{RiverGenerator, RiverStats};
+use crate::erosion::GlacialEroder;
+use crate::morphology;
+use crate::seed;
+use serde::Serialize;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Stand-in clock for wasm32, where `std::time::Instant::now` panics; stage
+/// timings read zero there.
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy)]
+struct Instant;
+
+#[cfg(target_arch = "wasm32")]
+impl Instant {
+    fn now() -> Self {
+        Instant
+    }
+    
+    fn duration_since(&self, _earlier: Instant) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
+
+/// Wall-clock time spent in one pipeline stage of the last `generate` call.
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    pub seconds: f64,
+}
+
+/// How a stage snapshot is best drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageView {
+    Elevation,
+    Climate,
+    Terrain,
+}
+
+/// Copy of the cell grid taken at the end of one pipeline stage.
+#[derive(Debug, Clone)]
+pub struct StageSnapshot {
+    pub name: &'static str,
+    pub view: StageView,
+    pub cells: Vec<Vec<TerrainCell>>,
+}
+
+/// How the `min_biomes` retries of the last `generate` call went. Both
+/// counts are 0 when `min_biomes` is unset.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct BiomeRetries {
+    /// Re-runs with wider climate spreads; 0 if the first world had enough.
+    pub retries: usize,
+    /// Distinct land biomes in the world kept.
+    pub land_biomes: usize,
+}
+
+/// How the `degenerate_retries` of the last `generate` call went.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DegenerateRetries {
+    /// Re-runs with re-derived seeds; 0 if the first world was usable.
+    pub retries: usize,
+    /// Whether the world kept is still degenerate.
+    pub degenerate: bool,
+    /// Water coverage of the world kept, in percent.
+    pub water_percentage: f32,
+}
 
 pub struct TerrainGenerator {
     width: u32,
     height: u32,
-    water_percentage: f32,
+    params: GenerationParams,
+    timings: Vec<StageTiming>,
+    capture_stages: bool,
+    snapshots: Vec<StageSnapshot>,
+    river_stats: RiverStats,
+    biome_retries: BiomeRetries,
+    degenerate_retries: DegenerateRetries,
+    cache_stages: bool,
+    checkpoint: Option<Checkpoint>,
+    noise: Option<Arc<dyn ElevationNoise>>,
+}
+
+/// What the early stages settle that later ones carry through to the
+/// finished `TerrainData` unchanged.
+#[derive(Clone)]
+struct Geography {
+    /// Width, height and world scale the grid was generated at.
+    grid: (u32, u32, f32),
+    plates: Vec<TectonicPlate>,
+    water_bodies: Vec<WaterBody>,
+    sea_levels: Vec<f32>,
+}
+
+/// State kept from the last `generate` so late stages can be re-run alone.
+struct Checkpoint {
     seed: u64,
+    geography: Geography,
+    /// Grid after the climate stage, before biomes.
+    climate: Vec<Vec<TerrainCell>>,
+    /// Grid and altitude lines after biomes, before rivers.
+    biomes: Option<(Vec<Vec<TerrainCell>>, AltitudeLines)>,
 }
 
 impl TerrainGenerator {
-    pub fn new(width: u32, height: u32, water_percentage: f32, seed: u64) -> Self {
+    pub fn new(width: u32, height: u32, params: GenerationParams) -> Self {
         Self {
             width,
             height,
-            water_percentage,
-            seed,
+            params,
+            timings: Vec::new(),
+            capture_stages: false,
+            snapshots: Vec::new(),
+            river_stats: RiverStats::default(),
+            biome_retries: BiomeRetries::default(),
+            degenerate_retries: DegenerateRetries::default(),
+            cache_stages: false,
+            checkpoint: None,
+            noise: None,
         }
     }
     
+    pub fn timings(&self) -> &[StageTiming] {
+        &self.timings
+    }
+    
+    /// Sample terrain noise from `noise` instead of the seeded Perlin field in
+    /// later `generate` and `animate_plates` calls, e.g. a fixed field for
+    /// testing. The seed still places plates.
+    pub fn set_noise(&mut self, noise: Arc<dyn ElevationNoise>) {
+        self.noise = Some(noise);
+    }
+    
+    /// Keep a copy of the grid after each stage of later `generate` calls.
+    pub fn capture_stages(&mut self, enabled: bool) {
+        self.capture_stages = enabled;
+    }
+    
+    /// Keep the post-climate and post-biome grids of later `generate` calls for
+    /// `regenerate_biomes` and `regenerate_rivers`. Costs two grid copies.
+    pub fn cache_stages(&mut self, enabled: bool) {
+        self.cache_stages = enabled;
+        if !enabled {
+            self.checkpoint = None;
+        }
+    }
+    
+    /// Parameters for later runs; see `regenerate_biomes` for which stages a
+    /// change reaches without a full `generate`.
+    pub fn params_mut(&mut self) -> &mut GenerationParams {
+        &mut self.params
+    }
+    
+    /// Snapshots from the last `generate` call, in pipeline order.
+    pub fn stage_snapshots(&self) -> &[StageSnapshot] {
+        &self.snapshots
+    }
+    
+    /// River source and outlet counts from the last `generate` call.
+    pub fn river_stats(&self) -> RiverStats {
+        self.river_stats
+    }
+    
+    /// Retries and biome count from the `min_biomes` check of the last
+    /// `generate` call.
+    pub fn biome_retries(&self) -> BiomeRetries {
+        self.biome_retries
+    }
+    
+    /// Retries and final water coverage from the degenerate-world check of
+    /// the last `generate` call.
+    pub fn degenerate_retries(&self) -> DegenerateRetries {
+        self.degenerate_retries
+    }
+    
+    /// Record a snapshot if capturing. The copy is not charged to any stage's timing.
+    fn capture(&mut self, name: &'static str, view: StageView, cells: &[Vec<TerrainCell>], start: &mut Instant) {
+        if self.capture_stages {
+            self.snapshots.push(StageSnapshot { name, view, cells: cells.to_vec() });
+            *start = Instant::now();
+        }
+    }
+    
+    fn finish_stage(&mut self, stage: &'static str, start: &mut Instant) {
+        let now = Instant::now();
+        self.timings.push(StageTiming {
+            stage,
+            seconds: now.duration_since(*start).as_secs_f64(),
+        });
+        *start = now;
+    }
+    
+    /// Generate a world. A degenerate result, whose water coverage is far from
+    /// the requested percentage, is retried with deterministically re-derived
+    /// seeds up to `degenerate_retries` times; the seed actually used is
+    /// recorded in the returned `generation_params`, and how the retries went
+    /// is available from the `degenerate_retries()` method.
     pub fn generate(&mut self) -> TerrainData {
-        let mut cells = vec![vec![TerrainCell {
+        let mut terrain = self.generate_varied();
+        
+        let original_seed = self.params.seed;
+        let mut attempt = 0;
+        while terrain.is_degenerate() && attempt < self.params.degenerate_retries {
+            attempt += 1;
+            self.params.seed = seed::derive_seed(original_seed, seed::STREAM_RESEED + attempt as u64);
+            terrain = self.generate_varied();
+        }
+        self.params.seed = original_seed;
+        
+        self.degenerate_retries = DegenerateRetries {
+            retries: attempt,
+            degenerate: terrain.is_degenerate(),
+            water_percentage: terrain.water_percentage(),
+        };
+        terrain
+    }
+    
+    /// With `min_biomes` set, a monotonous result is retried with progressively
+    /// wider temperature and rainfall spreads; the spreads actually used are
+    /// recorded in the returned `generation_params`.
+    fn generate_varied(&mut self) -> TerrainData {
+        const MAX_BIOME_RETRIES: usize = 5;
+        const SPREAD_STEP: f32 = 1.25;
+        
+        let mut terrain = self.generate_once();
+        if self.params.min_biomes == 0 {
+            self.biome_retries = BiomeRetries::default();
+            return terrain;
+        }
+        
+        let original_params = self.params.clone();
+        let mut attempt = 0;
+        while terrain.land_biome_count() < self.params.min_biomes && attempt < MAX_BIOME_RETRIES {
+            attempt += 1;
+            self.params.temperature_spread *= SPREAD_STEP;
+            self.params.rainfall_spread *= SPREAD_STEP;
+            terrain = self.generate_once();
+        }
+        self.params = original_params;
+        
+        self.biome_retries = BiomeRetries { retries: attempt, land_biomes: terrain.land_biome_count() };
+        terrain
+    }
+    
+    fn generate_once(&mut self) -> TerrainData {
+        let (width, height, world_scale) = self.grid_size();
+        self.timings.clear();
+        self.snapshots.clear();
+        let mut stage_start = Instant::now();
+        
+        let mut cells = Self::blank_cells(width, height);
+        
+        let plate_sim = self.plate_simulator(width, height, world_scale);
+        let plates = plate_sim.simulate(&mut cells);
+        self.apply_symmetry(&mut cells);
+        self.finish_stage("tectonics", &mut stage_start);
+        self.capture("plates", StageView::Elevation, &cells, &mut stage_start);
+        
+        // Terrain-only runs keep the blank climate, biome and river fields
+        let full = !self.params.terrain_only;
+        
+        // Temperature comes first because glacial carving reads it; moisture
+        // waits until the water it evaporates from is in place
+        let climate_sim = ClimateSimulator::new(width, height, world_scale, &self.params);
+        if full {
+            climate_sim.simulate_temperature(&mut cells);
+            self.finish_stage("temperature", &mut stage_start);
+        }
+        
+        let sea_levels = self.assign_water_bodies(&mut cells);
+        
+        if self.params.coast_smoothing > 0 {
+            self.smooth_coastline(&mut cells, &sea_levels);
+        }
+        if self.params.connect_islands > 0 {
+            self.connect_islands(&mut cells, &sea_levels);
+        }
+        if self.params.min_land_width > 0 {
+            self.separate_land_bridges(&mut cells, &sea_levels);
+        }
+        if self.params.min_island_size > 0 {
+            self.remove_small_islands(&mut cells, &sea_levels);
+        }
+        if self.params.fjords {
+            GlacialEroder::new(width, height, self.params.topology).carve_fjords(&mut cells);
+        }
+        let water_bodies = Self::label_water_bodies(&mut cells);
+        plate_sim.shape_ocean_floor(&mut cells, &plates, &sea_levels, self.params.ocean_floor);
+        self.apply_symmetry(&mut cells);
+        self.finish_stage("water", &mut stage_start);
+        self.capture("water", StageView::Elevation, &cells, &mut stage_start);
+        
+        if full {
+            climate_sim.set_water_temperatures(&mut cells, &sea_levels);
+            climate_sim.moderate_coastal_temperatures(&mut cells);
+            climate_sim.simulate_moisture(&mut cells);
+            self.apply_symmetry(&mut cells);
+            self.finish_stage("climate", &mut stage_start);
+            self.capture("climate", StageView::Climate, &cells, &mut stage_start);
+        }
+        
+        let geography = Geography { grid: (width, height, world_scale), plates, water_bodies, sea_levels };
+        if self.cache_stages {
+            self.checkpoint = Some(Checkpoint {
+                seed: self.params.seed,
+                geography: geography.clone(),
+                climate: cells.clone(),
+                biomes: None,
+            });
+        }
+        self.run_biomes(geography, cells, &mut stage_start)
+    }
+    
+    /// Re-run biome assignment and everything after it on the cached post-climate
+    /// grid of the last `generate`, using the current parameters. Only stages
+    /// from biomes on see parameter changes, the grid keeps the size it was
+    /// generated at even if `preview` changes, and the degenerate and
+    /// `min_biomes` retries are not repeated. `None` unless `cache_stages` was enabled.
+    pub fn regenerate_biomes(&mut self) -> Option<TerrainData> {
+        let checkpoint = self.checkpoint.as_ref()?;
+        let (geography, cells) = (checkpoint.geography.clone(), checkpoint.climate.clone());
+        Some(self.rerun(checkpoint.seed, |generator, start| generator.run_biomes(geography, cells, start)))
+    }
+    
+    /// Re-run river generation and the derived fields on the cached post-biome
+    /// grid of the last `generate`, as `regenerate_biomes` does for biomes.
+    pub fn regenerate_rivers(&mut self) -> Option<TerrainData> {
+        let checkpoint = self.checkpoint.as_ref()?;
+        let (cells, altitude_lines) = checkpoint.biomes.clone()?;
+        let geography = checkpoint.geography.clone();
+        Some(self.rerun(checkpoint.seed, |generator, start| {
+            generator.run_rivers(geography, cells, altitude_lines, start)
+        }))
+    }
+    
+    /// Run late stages under the seed the cached grid was generated with.
+    fn rerun(&mut self, seed: u64, stages: impl FnOnce(&mut Self, &mut Instant) -> TerrainData) -> TerrainData {
+        self.timings.clear();
+        self.snapshots.clear();
+        let mut stage_start = Instant::now();
+        
+        let original_seed = self.params.seed;
+        self.params.seed = seed;
+        let terrain = stages(self, &mut stage_start);
+        self.params.seed = original_seed;
+        terrain
+    }
+    
+    fn run_biomes(&mut self, geography: Geography, mut cells: Vec<Vec<TerrainCell>>,
+                  stage_start: &mut Instant) -> TerrainData {
+        let mut altitude_lines = AltitudeLines::default();
+        if !self.params.terrain_only {
+            let biome_assigner = BiomeAssigner::new(&self.params);
+            altitude_lines = biome_assigner.altitude_lines(&cells);
+            biome_assigner.assign_biomes(&mut cells, &altitude_lines);
+            self.apply_symmetry(&mut cells);
+            self.finish_stage("biomes", stage_start);
+            self.capture("biomes", StageView::Terrain, &cells, stage_start);
+        }
+        
+        if let Some(checkpoint) = &mut self.checkpoint {
+            checkpoint.biomes = Some((cells.clone(), altitude_lines.clone()));
+        }
+        self.run_rivers(geography, cells, altitude_lines, stage_start)
+    }
+    
+    /// The grid size comes from `geography`, as `cells` was generated at it,
+    /// and need not match the current parameters when re-running.
+    fn run_rivers(&mut self, geography: Geography, mut cells: Vec<Vec<TerrainCell>>,
+                  altitude_lines: AltitudeLines, stage_start: &mut Instant) -> TerrainData {
+        let Geography { grid: (width, height, world_scale), plates, water_bodies, sea_levels } = geography;
+        let full = !self.params.terrain_only;
+        
+        self.river_stats = RiverStats::default();
+        if full {
+            let river_gen = RiverGenerator::new(width, height, &self.params);
+            let (_, river_stats) = river_gen.generate_rivers(&mut cells);
+            self.apply_symmetry(&mut cells);
+            self.river_stats = river_stats;
+            self.finish_stage("rivers", stage_start);
+            self.capture("rivers", StageView::Terrain, &cells, stage_start);
+        }
+        
+        // Slope depends on river carving, so it is measured last
+        let topology = self.params.topology;
+        let slopes = crate::map_rows(height as usize, |y| {
+            (0..width as usize).map(|x| max_slope(&cells, topology, x, y)).collect::<Vec<_>>()
+        });
+        for (row, slope_row) in cells.iter_mut().zip(slopes) {
+            for (cell, slope) in row.iter_mut().zip(slope_row) {
+                cell.slope = slope;
+            }
+        }
+        let distances = coast_distances(&cells, self.params.topology);
+        for (row, distance_row) in cells.iter_mut().zip(distances) {
+            for (cell, distance) in row.iter_mut().zip(distance_row) {
+                cell.coast_distance = distance;
+            }
+        }
+        
+        let flow_field = if self.params.flow_vectors && full {
+            Some(ClimateSimulator::new(width, height, world_scale, &self.params).flow_field(&cells))
+        } else {
+            None
+        };
+        
+        TerrainData {
+            width,
+            height,
+            cells,
+            generation_params: GenerationParams {
+                plate_count: plates.len(),
+                ..self.params.clone()
+            },
+            plates,
+            flow_field,
+            snow_line: altitude_lines.snow_line,
+            tree_line: altitude_lines.tree_line,
+            water_bodies,
+            sea_levels,
+            elevation_cache: Default::default(),
+        }
+    }
+    
+    /// Elevation grids, row-major, for `frames` steps of plate drift on the
+    /// simulation grid, for animating tectonics.
+    pub fn animate_plates(&self, frames: usize) -> Vec<Vec<f32>> {
+        let (width, height, world_scale) = self.grid_size();
+        let mut cells = Self::blank_cells(width, height);
+        self.plate_simulator(width, height, world_scale).drift_frames(&mut cells, frames)
+    }
+    
+    fn plate_simulator(&self, width: u32, height: u32, world_scale: f32) -> PlateSimulator {
+        let mut plate_sim = PlateSimulator::new(width, height, world_scale, &self.params);
+        if let Some(noise) = &self.noise {
+            plate_sim.set_noise(noise.clone());
+        }
+        plate_sim
+    }
+    
+    fn blank_cells(width: u32, height: u32) -> Vec<Vec<TerrainCell>> {
+        vec![vec![TerrainCell {
             elevation: 0.0,
             temperature: 15.0,
             rainfall: 0.0,
@@ -30,54 +454,263 @@ impl TerrainGenerator {
             is_water: false,
             biome: BiomeType::Grassland,
             has_river: false,
-        }; self.width as usize]; self.height as usize];
+            land_bridge: false,
+            slope: 0.0,
+            flow_accumulation: 0,
+            is_freshwater: false,
+            water_body_id: None,
+            river_order: 0,
+            coast_distance: 0,
+            secondary_biome: None,
+            biome_blend: 0.0,
+        }; width as usize]; height as usize]
+    }
+    
+    /// Simulation grid size and world units per cell. Preview mode shrinks the
+    /// grid so the longer side is `PREVIEW_SIZE` while covering the same world.
+    fn grid_size(&self) -> (u32, u32, f32) {
+        const PREVIEW_SIZE: u32 = 128;
         
-        let mut plate_sim = PlateSimulator::new(self.width, self.height, self.seed);
-        let plates = plate_sim.simulate(&mut cells);
+        let longest = self.width.max(self.height);
+        if !self.params.preview || longest <= PREVIEW_SIZE {
+            return (self.width, self.height, 1.0);
+        }
+        
+        let scale = longest as f32 / PREVIEW_SIZE as f32;
+        let width = ((self.width as f32 / scale).round() as u32).max(1);
+        let height = ((self.height as f32 / scale).round() as u32).max(1);
+        (width, height, scale)
+    }
+    
+    /// Flood cells at or below sea level and return the sea level of each row.
+    /// The water mode adds a latitude term, so sea level may differ by row;
+    /// the percentile is taken over biased elevations so coverage still
+    /// matches `water_percentage`.
+    fn assign_water_bodies(&self, cells: &mut [Vec<TerrainCell>]) -> Vec<f32> {
+        let bias = self.latitude_water_bias(cells.len());
+        let mut biased: Vec<f32> = cells.iter().zip(&bias)
+            .flat_map(|(row, &b)| row.iter().map(move |cell| cell.elevation + b))
+            .collect();
+        biased.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let water_threshold = percentile_of_sorted(&biased, self.params.water_percentage)
+            .expect("terrain grid has no cells");
+        let sea_levels: Vec<f32> = bias.iter().map(|b| water_threshold - b).collect();
         
-        let climate_sim = ClimateSimulator::new(self.width, self.height);
-        climate_sim.simulate(&mut cells);
+        for (row, &sea_level) in cells.iter_mut().zip(&sea_levels) {
+            for cell in row.iter_mut() {
+                if cell.elevation <= sea_level {
+                    cell.is_water = true;
+                    cell.biome = BiomeType::Ocean;
+                }
+            }
+        }
         
-        self.assign_water_bodies(&mut cells);
+        sea_levels
+    }
+    
+    /// Elevation added to each row before the sea level comparison.
+    fn latitude_water_bias(&self, height: usize) -> Vec<f32> {
+        // Difference in effective elevation between equator and pole
+        const LATITUDE_BIAS: f32 = 1.0;
         
-        let biome_assigner = BiomeAssigner::new();
-        biome_assigner.assign_biomes(&mut cells);
+        (0..height).map(|y| {
+            let latitude = ((y as f32 + 0.5) / height as f32 - 0.5).abs() * 2.0;
+            match self.params.water_mode {
+                WaterMode::Elevation => 0.0,
+                WaterMode::Polar => -latitude * LATITUDE_BIAS,
+                WaterMode::Equatorial => latitude * LATITUDE_BIAS,
+            }
+        }).collect()
+    }
+    
+    /// Label each 8-connected body of water. Bodies that touch the map edge
+    /// are open sea; enclosed ones are freshwater lakes unless they cover
+    /// more than `MAX_LAKE_FRACTION` of the map, which makes them inland seas.
+    fn label_water_bodies(cells: &mut [Vec<TerrainCell>]) -> Vec<WaterBody> {
+        const MAX_LAKE_FRACTION: f32 = 0.01;
         
-        let river_gen = RiverGenerator::new(self.width, self.height);
-        river_gen.generate_rivers(&mut cells);
+        let water: Vec<Vec<bool>> = cells.iter()
+            .map(|row| row.iter().map(|cell| cell.is_water).collect())
+            .collect();
+        let (labels, count) = morphology::label_components(&water);
         
-        let plate_count = plates.len();
-        TerrainData {
-            width: self.width,
-            height: self.height,
-            cells,
-            plates,
-            generation_params: GenerationParams {
-                water_percentage: self.water_percentage,
-                seed: self.seed,
-                plate_count,
-            },
+        let height = labels.len();
+        let width = labels[0].len();
+        let mut bodies: Vec<WaterBody> = (0..count)
+            .map(|id| WaterBody { id, size: 0, is_connected_to_edge: false, is_lake: false })
+            .collect();
+        for (y, row) in labels.iter().enumerate() {
+            for (x, label) in row.iter().enumerate() {
+                if let Some(label) = label {
+                    bodies[*label].size += 1;
+                    if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                        bodies[*label].is_connected_to_edge = true;
+                    }
+                }
+            }
+        }
+        
+        let max_lake = (width * height) as f32 * MAX_LAKE_FRACTION;
+        for body in &mut bodies {
+            body.is_lake = !body.is_connected_to_edge && body.size as f32 <= max_lake;
+        }
+        
+        for (row, label_row) in cells.iter_mut().zip(&labels) {
+            for (cell, label) in row.iter_mut().zip(label_row) {
+                cell.water_body_id = *label;
+                cell.is_freshwater = label.is_some_and(|label| bodies[label].is_lake);
+            }
+        }
+        
+        bodies
+    }
+    
+    /// Overwrite the mirrored half of the grid with copies of the first half in
+    /// scan order. Called after every stage that could break symmetry, such as
+    /// directional winds or scan-order tie breaks. Water body ids stay as
+    /// labelled, since the mirrored lake is a body of its own.
+    fn apply_symmetry(&self, cells: &mut [Vec<TerrainCell>]) {
+        let symmetry = self.params.symmetry;
+        if symmetry == Symmetry::None {
+            return;
         }
+        
+        let height = cells.len();
+        let width = cells[0].len();
+        for y in 0..height {
+            for x in 0..width {
+                let (mx, my) = symmetry.mirror(x, y, width, height);
+                if (my, mx) >= (y, x) {
+                    continue;
+                }
+                let water_body_id = cells[y][x].water_body_id;
+                cells[y][x] = TerrainCell { water_body_id, ..cells[my][mx].clone() };
+            }
+        }
+    }
+    
+    fn land_mask(cells: &[Vec<TerrainCell>]) -> Vec<Vec<bool>> {
+        cells.iter()
+            .map(|row| row.iter().map(|cell| !cell.is_water).collect())
+            .collect()
     }
     
-    fn assign_water_bodies(&self, cells: &mut Vec<Vec<TerrainCell>>) {
-        let mut elevations: Vec<f32> = Vec::new();
+    /// Open then close the land mask, clearing single-cell islands and lakes
+    /// and rounding off jagged shoreline. Elevation is nudged across sea level
+    /// so later stages see the smoothed coast.
+    fn smooth_coastline(&self, cells: &mut [Vec<TerrainCell>], sea_levels: &[f32]) {
+        let land = Self::land_mask(cells);
+        let radius = self.params.coast_smoothing;
+        let smoothed = morphology::close(&morphology::open(&land, radius), radius);
         
-        for row in cells.iter() {
-            for cell in row.iter() {
-                elevations.push(cell.elevation);
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if smoothed[y][x] == land[y][x] {
+                    continue;
+                }
+                
+                if smoothed[y][x] {
+                    cell.is_water = false;
+                    cell.elevation = cell.elevation.max(sea_levels[y] + 0.01);
+                } else {
+                    cell.is_water = true;
+                    cell.biome = BiomeType::Ocean;
+                    cell.elevation = cell.elevation.min(sea_levels[y]);
+                }
             }
         }
+    }
+    
+    /// Find land necks that join two wider landmasses. Land a morphological
+    /// opening removes is narrow; where a connected piece of it touches two or
+    /// more of the surviving cores, the cells on near-shortest paths between
+    /// two cores are the neck. The rest of the piece, such as the coastal
+    /// fringe of either landmass, and isolated narrow land such as peninsulas
+    /// are left alone. An opening of radius r removes land up to 2r cells
+    /// wide, so odd widths are matched exactly, even ones also catch necks
+    /// exactly that wide, and a width of 1 finds nothing.
+    fn separate_land_bridges(&self, cells: &mut [Vec<TerrainCell>], sea_levels: &[f32]) {
+        let land = Self::land_mask(cells);
+        let radius = self.params.min_land_width / 2;
+        let cores = morphology::open(&land, radius);
         
-        elevations.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        let water_threshold_index = (elevations.len() as f32 * self.water_percentage / 100.0) as usize;
-        let water_threshold = elevations[water_threshold_index.min(elevations.len() - 1)];
+        let narrow: Vec<Vec<bool>> = land.iter().zip(&cores)
+            .map(|(land_row, core_row)| {
+                land_row.iter().zip(core_row).map(|(&l, &c)| l && !c).collect()
+            })
+            .collect();
         
-        for row in cells.iter_mut() {
-            for cell in row.iter_mut() {
-                if cell.elevation <= water_threshold {
+        let (core_labels, _) = morphology::label_components(&cores);
+        let (narrow_labels, narrow_count) = morphology::label_components(&narrow);
+        let necks = morphology::necks(&narrow_labels, narrow_count, &core_labels, radius as u32);
+        
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !necks[y][x] {
+                    continue;
+                }
+                
+                if self.params.flag_land_bridges {
+                    cell.land_bridge = true;
+                } else {
+                    cell.is_water = true;
+                    cell.biome = BiomeType::Ocean;
+                    cell.elevation = cell.elevation.min(sea_levels[y]);
+                }
+            }
+        }
+    }
+    
+    /// Flood 8-connected land components smaller than `min_island_size` cells.
+    fn remove_small_islands(&self, cells: &mut [Vec<TerrainCell>], sea_levels: &[f32]) {
+        let (labels, count) = morphology::label_components(&Self::land_mask(cells));
+        let mut sizes = vec![0usize; count];
+        for label in labels.iter().flatten().flatten() {
+            sizes[*label] += 1;
+        }
+        
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let Some(island) = labels[y][x] else { continue };
+                if sizes[island] < self.params.min_island_size {
                     cell.is_water = true;
                     cell.biome = BiomeType::Ocean;
+                    cell.elevation = cell.elevation.min(sea_levels[y]);
+                }
+            }
+        }
+    }
+    
+    /// Raise shallow water gaps that a morphological closing would fill, when the
+    /// gap joins two or more separate landmasses.
+    fn connect_islands(&self, cells: &mut [Vec<TerrainCell>], sea_levels: &[f32]) {
+        // Only water this close below sea level counts as a shallow strait
+        const SHALLOW_DEPTH: f32 = 0.2;
+        
+        let land = Self::land_mask(cells);
+        let radius = self.params.connect_islands.div_ceil(2);
+        let closed = morphology::close(&land, radius);
+        
+        let straits: Vec<Vec<bool>> = closed.iter().enumerate()
+            .map(|(y, row)| {
+                row.iter().enumerate()
+                    .map(|(x, &c)| {
+                        c && !land[y][x] && cells[y][x].elevation > sea_levels[y] - SHALLOW_DEPTH
+                    })
+                    .collect()
+            })
+            .collect();
+        
+        let (land_labels, _) = morphology::label_components(&land);
+        let (strait_labels, strait_count) = morphology::label_components(&straits);
+        let touching = morphology::touching_labels(&strait_labels, strait_count, &land_labels);
+        
+        for (y, row) in cells.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let Some(strait) = strait_labels[y][x] else { continue };
+                if touching[strait].len() >= 2 {
+                    cell.is_water = false;
+                    cell.elevation = cell.elevation.max(sea_levels[y] + 0.01);
                 }
             }
         }
@@ -0,0 +1,12 @@
+//! Plate-drift GIF export.
+#![cfg(feature = "animate")]
+
+use terrain_generator::output::export_plate_animation;
+
+#[test]
+fn oversized_gif_is_an_error() {
+    let path = std::env::temp_dir().join("terrain_oversized_animation.gif");
+    let result = export_plate_animation(&[], 4096, 64, 50.0, path.to_str().unwrap(), 16);
+    assert!(result.unwrap_err().to_string().contains("too large for a GIF"));
+    assert!(!path.exists());
+}
\ No newline at end of file
@@ -0,0 +1,121 @@
+//! Biome assignment on hand-built grids.
+
+mod common;
+
+use common::flat_land;
+use terrain_generator::biomes::{default_biome_rules, BiomeAssigner};
+use terrain_generator::climate::LAPSE_RATE;
+use terrain_generator::{BiomeType, GenerationParams, TerrainCell};
+
+/// Sea in the two left columns, then land at `shore` elevation in column 2
+/// and `inland` elevation beyond.
+fn coast(shore: f32, inland: f32) -> Vec<Vec<TerrainCell>> {
+    let mut cells = flat_land(12, 6);
+    for row in cells.iter_mut() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            cell.rainfall = 5.0;
+            match x {
+                0 | 1 => cell.is_water = true,
+                2 => cell.elevation = shore,
+                _ => cell.elevation = inland,
+            }
+        }
+    }
+    cells
+}
+
+fn assign(cells: &mut [Vec<TerrainCell>], params: &GenerationParams) {
+    let assigner = BiomeAssigner::new(params);
+    let lines = assigner.altitude_lines(cells);
+    assigner.assign_biomes(cells, &lines);
+}
+
+#[test]
+fn gentle_shore_grows_a_beach_inland() {
+    let mut cells = coast(0.01, 0.01);
+    assign(&mut cells, &GenerationParams { beach_width: 4, ..GenerationParams::default() });
+    assert!(cells.iter().all(|row| row[2].biome == BiomeType::Beach && row[3].biome == BiomeType::Beach));
+}
+
+#[test]
+fn no_beach_behind_a_cliff() {
+    let mut cells = coast(0.5, 0.5);
+    assign(&mut cells, &GenerationParams { beach_width: 4, ..GenerationParams::default() });
+    assert!(cells.iter().flatten().all(|cell| cell.biome != BiomeType::Beach));
+}
+
+#[test]
+fn no_beaches_still_grows_mangroves() {
+    let mut cells = coast(0.01, 0.01);
+    for cell in cells.iter_mut().flatten() {
+        cell.temperature = 28.0;
+        cell.rainfall = 15.0;
+    }
+    assign(&mut cells, &GenerationParams { no_beaches: true, ..GenerationParams::default() });
+    assert!(cells.iter().flatten().all(|cell| cell.biome != BiomeType::Beach));
+    assert!(cells.iter().all(|row| row[2].biome == BiomeType::Mangrove));
+}
+
+#[test]
+fn altitude_lines_ignore_water_temperature() {
+    let mut cells = coast(0.1, 0.1);
+    for row in cells.iter_mut() {
+        row[0].temperature = -20.0;
+        row[1].temperature = -20.0;
+    }
+    let lines = BiomeAssigner::new(&GenerationParams::default()).altitude_lines(&cells);
+    let expected = (15.0 + 0.1 * LAPSE_RATE) / LAPSE_RATE;
+    assert!(lines.snow_line.iter().all(|&line| (line - expected).abs() < 1e-4));
+}
+
+#[test]
+fn cold_row_does_not_glaciate_thawed_land() {
+    let mut cells = coast(0.2, 0.5);
+    for row in cells.iter_mut() {
+        for cell in row.iter_mut().skip(3) {
+            cell.temperature = -15.0;
+        }
+    }
+    assign(&mut cells, &GenerationParams::default());
+    assert!(cells.iter().all(|row| row[2].biome != BiomeType::Glacier));
+    assert!(cells.iter().all(|row| row[6].biome == BiomeType::Glacier));
+}
+
+/// The climate tree the built-in rules table replaced.
+fn climate_biome(temp: f32, rainfall: f32) -> BiomeType {
+    if temp < -5.0 {
+        return BiomeType::Tundra;
+    }
+    if rainfall < 1.5 {
+        if temp > 25.0 {
+            BiomeType::Desert
+        } else if temp > 10.0 {
+            BiomeType::Grassland
+        } else {
+            BiomeType::Tundra
+        }
+    } else if rainfall > 12.0 && temp > 22.0 {
+        BiomeType::Rainforest
+    } else if rainfall > 6.0 && temp > 5.0 {
+        BiomeType::Forest
+    } else if rainfall > 3.0 || temp > 15.0 {
+        BiomeType::Grassland
+    } else {
+        BiomeType::Tundra
+    }
+}
+
+#[test]
+fn default_rules_match_the_climate_tree() {
+    let rules = default_biome_rules();
+    let mut cell = flat_land(1, 1).remove(0).remove(0);
+    // Quarter-degree steps land exactly on every threshold in the table
+    for t in -120..=180 {
+        for r in 0..=64 {
+            cell.temperature = t as f32 * 0.25;
+            cell.rainfall = r as f32 * 0.25;
+            let biome = rules.iter().find(|rule| rule.matches(&cell)).map(|rule| rule.biome);
+            assert_eq!(biome, Some(climate_biome(cell.temperature, cell.rainfall)), "{} C, {} rain", cell.temperature, cell.rainfall);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+//! Argument checks and messages of the command-line front end.
+
+use std::process::Command;
+
+fn run(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_terrain-generator")).args(args).output().expect("failed to run the binary")
+}
+
+#[test]
+fn non_finite_flip_chance_is_rejected() {
+    for value in ["NaN", "inf", "-inf"] {
+        let output = run(&[&format!("--oceanic-flip-chance={}", value)]);
+        assert_eq!(output.status.code(), Some(2), "{} was accepted", value);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("not a finite number"));
+    }
+}
+
+/// Output prefix in a scratch directory of its own.
+fn scratch(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("terrain_cli_{}", name));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("terrain").to_str().unwrap().to_string()
+}
+
+#[test]
+fn biome_warning_respects_quiet() {
+    let output = scratch("biome_warning");
+    let args = ["-w", "100", "-H", "100", "--min-biomes", "20", "-o", &output];
+    let loud = run(&args);
+    assert!(loud.status.success());
+    assert!(String::from_utf8_lossy(&loud.stderr).contains("distinct land biomes after 5 retries"));
+    
+    let quiet = run(&[&args[..], &["-q"]].concat());
+    assert!(quiet.status.success());
+    assert!(quiet.stderr.is_empty(), "{}", String::from_utf8_lossy(&quiet.stderr));
+}
+
+#[cfg(not(feature = "parallel"))]
+#[test]
+fn threads_needs_the_parallel_feature() {
+    let output = run(&["--threads", "4"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--threads"));
+}
\ No newline at end of file
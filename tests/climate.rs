@@ -0,0 +1,115 @@
+//! Climate passes that depend on where the water is.
+
+mod common;
+
+use common::{flat_land, generate_world};
+use terrain_generator::climate::ClimateSimulator;
+use terrain_generator::{GenerationParams, TerrainData};
+
+fn land_rainfall(terrain: &TerrainData) -> Vec<f32> {
+    terrain.cells.iter().flatten().filter(|cell| !cell.is_water).map(|cell| cell.rainfall).collect()
+}
+
+#[test]
+fn water_temperature_reaches_land_rainfall() {
+    // Mixing changes only water temperatures, so any rainfall difference on
+    // land has come through evaporation
+    let still = generate_world(GenerationParams { seed: 7, ..GenerationParams::default() });
+    let mixed = generate_world(GenerationParams { seed: 7, ocean_mixing: 200, ..GenerationParams::default() });
+    
+    let land = |terrain: &TerrainData| terrain.cells.iter().flatten().map(|cell| cell.is_water).collect::<Vec<_>>();
+    assert_eq!(land(&still), land(&mixed));
+    assert_ne!(land_rainfall(&still), land_rainfall(&mixed));
+}
+
+#[test]
+fn convection_strength_changes_rainfall() {
+    let calm = generate_world(GenerationParams { seed: 7, convection_strength: 0.0, ..GenerationParams::default() });
+    let stormy = generate_world(GenerationParams {
+        seed: 7,
+        convection_strength: 100.0,
+        convection_threshold: 0.0,
+        ..GenerationParams::default()
+    });
+    
+    let total = |terrain: &TerrainData| land_rainfall(terrain).iter().sum::<f32>();
+    assert!(total(&stormy) > total(&calm));
+}
+
+#[test]
+fn wind_moves_rainfall_over_land_without_adding_to_it() {
+    // With no latitude gradient, flat land all gets the same local rainfall,
+    // so the wind can only shift it around. Cells away from the map edge pass
+    // on about what they receive, so the median is that local rainfall
+    let params = GenerationParams { temperature_spread: 0.0, ..GenerationParams::default() };
+    let climate = ClimateSimulator::new(32, 16, 1.0, &params);
+    let mut cells = flat_land(32, 16);
+    climate.simulate_temperature(&mut cells);
+    climate.simulate_moisture(&mut cells);
+    
+    let mut rainfall: Vec<f32> = cells.iter().flatten().map(|cell| cell.rainfall).collect();
+    rainfall.sort_by(f32::total_cmp);
+    let local = rainfall[rainfall.len() / 2];
+    let total: f32 = rainfall.iter().sum();
+    assert!(rainfall.iter().any(|&r| r != local), "the wind moved nothing");
+    assert!((total - local * rainfall.len() as f32).abs() < 1e-3 * total);
+}
+
+#[test]
+fn moisture_range_rains_out_the_air_without_losing_rainfall() {
+    let rainfall = |moisture_range| {
+        let params = GenerationParams { temperature_spread: 0.0, moisture_range, ..GenerationParams::default() };
+        let climate = ClimateSimulator::new(32, 16, 1.0, &params);
+        let mut cells = flat_land(32, 16);
+        climate.simulate_temperature(&mut cells);
+        climate.simulate_moisture(&mut cells);
+        cells.iter().flatten().map(|cell| cell.rainfall).collect::<Vec<f32>>()
+    };
+    let (far, near) = (rainfall(0.0), rainfall(2.0));
+    
+    // Less moisture travels far, so less piles up at the downwind ends of rows
+    assert_ne!(far, near);
+    let spread = |values: &[f32]| {
+        values.iter().cloned().fold(f32::NEG_INFINITY, f32::max) - values.iter().cloned().fold(f32::INFINITY, f32::min)
+    };
+    assert!(spread(&near) < spread(&far));
+    let (total_far, total_near) = (far.iter().sum::<f32>(), near.iter().sum::<f32>());
+    assert!((total_far - total_near).abs() < 1e-3 * total_far);
+}
+
+#[test]
+fn maritime_moderation_fades_inland() {
+    let params = GenerationParams { maritime_moderation: 1.0, ..GenerationParams::default() };
+    let climate = ClimateSimulator::new(32, 4, 1.0, &params);
+    let mut cells = flat_land(32, 4);
+    for row in cells.iter_mut() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            cell.is_water = x == 0;
+            cell.temperature = if x == 0 { 0.0 } else { 20.0 };
+        }
+    }
+    climate.moderate_coastal_temperatures(&mut cells);
+    
+    let row = &cells[1];
+    assert!(row[1].temperature < row[5].temperature);
+    assert!(row[5].temperature < 20.0);
+    assert_eq!(row[25].temperature, 20.0);
+}
+
+#[test]
+fn currents_follow_the_wind_over_water_only() {
+    let climate = ClimateSimulator::new(8, 16, 1.0, &GenerationParams::default());
+    let mut cells = flat_land(8, 16);
+    for row in cells.iter_mut() {
+        row[0].is_water = true;
+    }
+    let flow = climate.flow_field(&cells);
+    
+    for (y, row) in flow.current.iter().enumerate() {
+        assert!(row[1..].iter().all(|&current| current == (0.0, 0.0)));
+        let (wx, wy) = flow.wind[y][0];
+        let (cx, cy) = row[0];
+        let ratio = (cx * cx + cy * cy).sqrt() / (wx * wx + wy * wy).sqrt();
+        assert!((ratio - 0.03).abs() < 1e-4, "row {} current is {} of the wind", y, ratio);
+    }
+}
\ No newline at end of file
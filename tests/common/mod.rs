@@ -0,0 +1,32 @@
+//! Helpers shared by the integration tests. Each test crate uses only some.
+#![allow(dead_code)]
+
+use terrain_generator::terrain::TerrainGenerator;
+use terrain_generator::{BiomeType, GenerationParams, TerrainCell, TerrainData};
+
+/// A full run of the generator on a 128x128 grid.
+pub fn generate_world(params: GenerationParams) -> TerrainData {
+    TerrainGenerator::new(128, 128, params).generate()
+}
+
+/// A `width` x `height` grid of dry land at sea level.
+pub fn flat_land(width: usize, height: usize) -> Vec<Vec<TerrainCell>> {
+    vec![vec![TerrainCell {
+        elevation: 0.0,
+        temperature: 15.0,
+        rainfall: 0.0,
+        plate_id: 0,
+        is_water: false,
+        biome: BiomeType::Grassland,
+        has_river: false,
+        land_bridge: false,
+        slope: 0.0,
+        flow_accumulation: 0,
+        is_freshwater: false,
+        water_body_id: None,
+        river_order: 0,
+        coast_distance: 0,
+        secondary_biome: None,
+        biome_blend: 0.0,
+    }; width]; height]
+}
\ No newline at end of file
@@ -0,0 +1,48 @@
+//! Plate boundary GeoJSON export.
+#![cfg(feature = "fs")]
+
+mod common;
+
+use common::generate_world;
+use std::collections::BTreeSet;
+use terrain_generator::output::{export_plate_boundaries_geojson, GeoBounds};
+use terrain_generator::GenerationParams;
+
+#[test]
+fn boundaries_cover_every_plate_pair_within_bounds() {
+    let terrain = generate_world(GenerationParams { seed: 4, ..GenerationParams::default() });
+    let path = std::env::temp_dir().join("terrain_plate_boundaries.geojson");
+    let bounds = GeoBounds::default();
+    export_plate_boundaries_geojson(&terrain, path.to_str().unwrap(), bounds).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    
+    let mut pairs = BTreeSet::new();
+    for feature in json["features"].as_array().unwrap() {
+        let properties = &feature["properties"];
+        let plates = properties["plates"].as_array().unwrap();
+        pairs.insert((plates[0].as_u64().unwrap() as usize, plates[1].as_u64().unwrap() as usize));
+        
+        let convergence = properties["convergence"].as_f64().unwrap();
+        let expected = if convergence > 0.0 { "convergent" } else { "divergent" };
+        assert_eq!(properties["boundary"], expected);
+        
+        for point in feature["geometry"]["coordinates"].as_array().unwrap() {
+            let (lon, lat) = (point[0].as_f64().unwrap(), point[1].as_f64().unwrap());
+            assert!((bounds.min_lon..=bounds.max_lon).contains(&lon));
+            assert!((bounds.min_lat..=bounds.max_lat).contains(&lat));
+        }
+    }
+    
+    let mut expected = BTreeSet::new();
+    for (x, y, cell) in terrain.iter_cells() {
+        for (nx, ny) in [(x + 1, y), (x, y + 1)] {
+            if let Some(other) = terrain.cells.get(ny).and_then(|row| row.get(nx)) {
+                if other.plate_id != cell.plate_id {
+                    expected.insert((cell.plate_id.min(other.plate_id), cell.plate_id.max(other.plate_id)));
+                }
+            }
+        }
+    }
+    assert_eq!(pairs, expected);
+}
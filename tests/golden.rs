@@ -0,0 +1,69 @@
+//! Golden-value regression test: a small fixed-seed world must hash to the
+//! committed value. If a change is meant to alter output, regenerate the hash
+//! from the failure message and update `GOLDEN_HASH`, once with and once
+//! without the `f64-elevation` feature.
+
+use terrain_generator::terrain::TerrainGenerator;
+use terrain_generator::{GenerationParams, TerrainData};
+
+#[cfg(not(feature = "f64-elevation"))]
+const GOLDEN_HASH: u64 = 0x280f_a79b_aeeb_eafd;
+/// Elevations are hashed at double precision, so they need their own value.
+#[cfg(feature = "f64-elevation")]
+const GOLDEN_HASH: u64 = 0x330d_141e_b28d_1323;
+
+/// FNV-1a, so the hash does not depend on the standard library's hasher.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+    
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Hashes every simulated field of every cell, plus the plates.
+fn hash_terrain(terrain: &TerrainData) -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(&terrain.width.to_le_bytes());
+    hasher.write(&terrain.height.to_le_bytes());
+    for row in &terrain.cells {
+        for cell in row {
+            hasher.write(&cell.elevation.to_bits().to_le_bytes());
+            hasher.write(&cell.temperature.to_bits().to_le_bytes());
+            hasher.write(&cell.rainfall.to_bits().to_le_bytes());
+            hasher.write(&cell.slope.to_bits().to_le_bytes());
+            hasher.write(&cell.biome_blend.to_bits().to_le_bytes());
+            hasher.write(&cell.coast_distance.to_le_bytes());
+            hasher.write(&(cell.plate_id as u64).to_le_bytes());
+            hasher.write(&cell.flow_accumulation.to_le_bytes());
+            hasher.write(&(cell.water_body_id.map_or(u64::MAX, |id| id as u64)).to_le_bytes());
+            hasher.write(&[cell.secondary_biome.map_or(u8::MAX, |biome| biome as u8)]);
+            hasher.write(&[cell.biome as u8, cell.is_water as u8, cell.has_river as u8, cell.land_bridge as u8, cell.is_freshwater as u8, cell.river_order]);
+        }
+    }
+    for plate in &terrain.plates {
+        hasher.write(&plate.center.0.to_bits().to_le_bytes());
+        hasher.write(&plate.center.1.to_bits().to_le_bytes());
+        hasher.write(&plate.velocity.0.to_bits().to_le_bytes());
+        hasher.write(&plate.velocity.1.to_bits().to_le_bytes());
+    }
+    hasher.0
+}
+
+#[test]
+fn fixed_seed_output_matches_golden_hash() {
+    let params = GenerationParams {
+        seed: 42,
+        ..GenerationParams::default()
+    };
+    let terrain = TerrainGenerator::new(128, 128, params).generate();
+    let hash = hash_terrain(&terrain);
+    assert_eq!(hash, GOLDEN_HASH, "terrain output changed: new hash is {:#018x}", hash);
+}
\ No newline at end of file
@@ -0,0 +1,62 @@
+//! Graticule lines and labels drawn over rendered maps.
+
+mod common;
+
+use common::generate_world;
+use terrain_generator::output::{render_to_rgb_buffer, RenderOptions};
+use terrain_generator::terrain::TerrainGenerator;
+use terrain_generator::{GenerationParams, Projection, TerrainData};
+
+const SCALE: u32 = 4;
+
+fn params(projection: Projection) -> GenerationParams {
+    GenerationParams { seed: 3, projection, terrain_only: true, ..GenerationParams::default() }
+}
+
+/// The rendered pixels with and without a graticule at `spacing`.
+fn render(terrain: &TerrainData, spacing: f32) -> (Vec<[u8; 3]>, Vec<[u8; 3]>) {
+    let pixels = |graticule| {
+        let options = RenderOptions { scale: SCALE, graticule, ..RenderOptions::default() };
+        render_to_rgb_buffer(terrain, &options).chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect()
+    };
+    (pixels(0.0), pixels(spacing))
+}
+
+#[test]
+fn parallels_put_the_poles_on_the_edge_rows() {
+    // With 121 rows from pole to pole each row is 1.5 degrees, so every
+    // parallel runs through the middle of a row
+    let terrain = TerrainGenerator::new(128, 121, params(Projection::Equirectangular)).generate();
+    let (plain, drawn) = render(&terrain, 30.0);
+    let width = (terrain.width * SCALE) as usize;
+    let lighten = |[r, g, b]: [u8; 3]| [r, g, b].map(|c| (c as f32 + (255.0 - c as f32) * 0.35).round() as u8);
+    
+    let lines: Vec<usize> = (0..(terrain.height * SCALE) as usize)
+        .filter(|&y| {
+            (0..width).all(|x| {
+                let i = y * width + x;
+                // Crossings are lightened twice and labels are solid white
+                [lighten(plain[i]), lighten(lighten(plain[i])), [255, 255, 255]].contains(&drawn[i])
+            })
+        })
+        .collect();
+    let centres: Vec<usize> = (1..6).map(|k| k * 20 * SCALE as usize + SCALE as usize / 2).collect();
+    assert_eq!(lines, centres);
+}
+
+#[test]
+fn fractional_spacing_labels_show_the_decimal_point() {
+    // The meridian at 12.5 cells is at pixel 50, labelled "12.5" from pixel
+    // 58 with glyphs four font pixels apart; the point is the lowest pixel of
+    // the third glyph's centre column
+    let terrain = generate_world(params(Projection::Flat));
+    let (_, drawn) = render(&terrain, 12.5);
+    let width = (terrain.width * SCALE) as usize;
+    let size = SCALE as usize;
+    let (left, top) = (58 + 2 * 4 * size + size, size + 4 * size);
+    for y in top..top + size {
+        for x in left..left + size {
+            assert_eq!(drawn[y * width + x], [255, 255, 255], "({}, {})", x, y);
+        }
+    }
+}
\ No newline at end of file
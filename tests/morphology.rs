@@ -0,0 +1,83 @@
+//! Binary morphology on small hand-drawn masks.
+
+use terrain_generator::morphology::{close, label_components, necks, open};
+
+/// Parse rows of `#` (set) and `.` (unset) into a mask.
+fn mask(rows: &[&str]) -> Vec<Vec<bool>> {
+    rows.iter().map(|row| row.chars().map(|c| c == '#').collect()).collect()
+}
+
+fn count(mask: &[Vec<bool>]) -> usize {
+    mask.iter().flatten().filter(|&&set| set).count()
+}
+
+#[test]
+fn opening_removes_thin_land_and_closing_fills_thin_gaps() {
+    let land = mask(&[
+        "..........",
+        ".####.....",
+        ".####.#...",
+        ".####.....",
+        ".####.....",
+        "..........",
+    ]);
+    let opened = open(&land, 1);
+    assert!(!opened[2][6], "single cell survived opening");
+    assert_eq!(count(&opened), 16);
+    
+    let gap = mask(&[
+        "#########",
+        "####.####",
+        "#########",
+    ]);
+    assert_eq!(count(&close(&gap, 1)), 27);
+}
+
+#[test]
+fn labels_are_eight_connected() {
+    let diagonal = mask(&[
+        "#...",
+        ".#..",
+        "...#",
+    ]);
+    let (labels, components) = label_components(&diagonal);
+    assert_eq!(components, 2);
+    assert_eq!(labels[0][0], labels[1][1]);
+    assert_ne!(labels[1][1], labels[2][3]);
+}
+
+#[test]
+fn neck_between_two_landmasses_is_found_but_not_their_fringe() {
+    // Two blocks joined by a thin neck, whose ragged bottom coasts are
+    // narrow land connected to the neck
+    let land = mask(&[
+        "..............................",
+        ".########..........#########..",
+        ".########..........#########..",
+        ".########..........#########..",
+        ".########..........#########..",
+        ".########..........#########..",
+        ".###########################..",
+        "#.#.#.#.#..........#.#.#.#.#..",
+        ".#.#.#.#............#.#.#.#...",
+        "..............................",
+    ]);
+    let cores = open(&land, 1);
+    let narrow: Vec<Vec<bool>> = land.iter().zip(&cores)
+        .map(|(l, c)| l.iter().zip(c).map(|(&l, &c)| l && !c).collect())
+        .collect();
+    let (core_labels, core_count) = label_components(&cores);
+    let (narrow_labels, narrow_count) = label_components(&narrow);
+    assert_eq!(core_count, 2);
+    
+    assert_eq!(narrow_labels[7][0], narrow_labels[6][13], "fringe not joined to the neck");
+    let found = necks(&narrow_labels, narrow_count, &core_labels, 1);
+    assert!(found[6][9..19].iter().all(|&neck| neck), "neck cell missed");
+    assert!(!found[7][0] && !found[8][1] && !found[7][2], "fringe flagged as neck");
+    
+    let separated: Vec<Vec<bool>> = land.iter().zip(&found)
+        .map(|(l, n)| l.iter().zip(n).map(|(&l, &n)| l && !n).collect())
+        .collect();
+    let (labels, _) = label_components(&separated);
+    assert_ne!(labels[3][3], labels[3][22]);
+}
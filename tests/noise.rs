@@ -0,0 +1,39 @@
+//! Injecting a custom noise field into terrain generation.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use terrain_generator::noise_source::ElevationNoise;
+use terrain_generator::terrain::TerrainGenerator;
+use terrain_generator::{GenerationParams, TerrainData};
+
+/// The same value everywhere, counting how often it is asked for.
+struct ConstantNoise {
+    value: f64,
+    samples: AtomicUsize,
+}
+
+impl ElevationNoise for ConstantNoise {
+    fn sample(&self, _x: f64, _y: f64, _octave: u32) -> f64 {
+        self.samples.fetch_add(1, Ordering::Relaxed);
+        self.value
+    }
+}
+
+fn elevations(terrain: &TerrainData) -> Vec<f32> {
+    terrain.cells.iter().flatten().map(|cell| cell.elevation).collect()
+}
+
+#[test]
+fn generation_samples_the_injected_noise() {
+    let params = GenerationParams { seed: 9, terrain_only: true, ..GenerationParams::default() };
+    let noise = Arc::new(ConstantNoise { value: 0.0, samples: AtomicUsize::new(0) });
+    
+    let mut generator = TerrainGenerator::new(128, 128, params.clone());
+    generator.set_noise(noise.clone());
+    let constant = generator.generate();
+    assert!(noise.samples.load(Ordering::Relaxed) > 0);
+    
+    let perlin = TerrainGenerator::new(128, 128, params).generate();
+    assert_ne!(elevations(&constant), elevations(&perlin));
+    assert_eq!(elevations(&generator.generate()), elevations(&constant));
+}
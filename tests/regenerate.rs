@@ -0,0 +1,41 @@
+//! Re-running late stages from the cached grids.
+
+use terrain_generator::terrain::TerrainGenerator;
+use terrain_generator::GenerationParams;
+
+fn cached_generator(width: u32, height: u32, params: GenerationParams) -> TerrainGenerator {
+    let mut generator = TerrainGenerator::new(width, height, params);
+    generator.cache_stages(true);
+    generator
+}
+
+#[test]
+fn regenerating_with_unchanged_params_reproduces_generate() {
+    let mut generator = cached_generator(128, 128, GenerationParams { seed: 3, ..GenerationParams::default() });
+    let generated = serde_json::to_string(&generator.generate()).unwrap();
+    
+    let biomes = generator.regenerate_biomes().expect("stages were cached");
+    assert_eq!(serde_json::to_string(&biomes).unwrap(), generated);
+    let rivers = generator.regenerate_rivers().expect("stages were cached");
+    assert_eq!(serde_json::to_string(&rivers).unwrap(), generated);
+}
+
+#[test]
+fn regenerating_keeps_the_cached_grid_size() {
+    let mut generator = cached_generator(256, 256, GenerationParams { seed: 3, preview: true, ..GenerationParams::default() });
+    let generated = generator.generate();
+    
+    generator.params_mut().preview = false;
+    let biomes = generator.regenerate_biomes().expect("stages were cached");
+    assert_eq!((biomes.width, biomes.height), (generated.width, generated.height));
+    let rivers = generator.regenerate_rivers().expect("stages were cached");
+    assert_eq!((rivers.width, rivers.height), (generated.width, generated.height));
+}
+
+#[test]
+fn nothing_to_regenerate_without_caching() {
+    let mut generator = TerrainGenerator::new(128, 128, GenerationParams::default());
+    generator.generate();
+    assert!(generator.regenerate_biomes().is_none());
+    assert!(generator.regenerate_rivers().is_none());
+}
@@ -0,0 +1,84 @@
+//! River tracing against flow accumulation.
+
+mod common;
+
+use common::flat_land;
+use terrain_generator::rivers::RiverGenerator;
+use terrain_generator::{GenerationParams, TerrainCell};
+
+/// Wet land sloping down to a sea along the bottom rows, rippled so flow
+/// paths wander, with a row of peaks near the top to start rivers.
+fn wet_slope() -> Vec<Vec<TerrainCell>> {
+    let mut cells = flat_land(64, 64);
+    for (y, row) in cells.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let ripple = 0.05 * (x as f32 * 0.7).sin() * (y as f32 * 0.5).cos();
+            cell.elevation = 1.6 - 0.025 * y as f32 + ripple;
+            if y == 4 && x % 8 == 4 {
+                cell.elevation += 0.4;
+            }
+            cell.rainfall = 10.0;
+            if y >= 58 {
+                cell.elevation = -0.1;
+                cell.is_water = true;
+            }
+        }
+    }
+    cells
+}
+
+#[test]
+fn accumulation_grows_along_every_river() {
+    let mut cells = wet_slope();
+    let (network, stats) = RiverGenerator::new(64, 64, &GenerationParams::default()).generate_rivers(&mut cells);
+    assert!(stats.rivers > 0);
+    
+    let mut links = 0;
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if let Some((nx, ny)) = network.next(x, y) {
+                assert!(cells[ny][nx].flow_accumulation > cell.flow_accumulation,
+                    "accumulation falls from ({}, {}) to ({}, {})", x, y, nx, ny);
+                links += 1;
+            }
+        }
+    }
+    assert!(links > 0);
+}
+
+
+#[test]
+fn river_order_follows_strahler_rule() {
+    let mut cells = wet_slope();
+    let (network, _) = RiverGenerator::new(64, 64, &GenerationParams::default()).generate_rivers(&mut cells);
+    
+    let mut tributaries = vec![vec![Vec::new(); 64]; 64];
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if let Some((nx, ny)) = network.next(x, y) {
+                tributaries[ny][nx].push(cell.river_order);
+            }
+        }
+    }
+    
+    let mut confluences = 0;
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if cell.river_order == 0 {
+                continue;
+            }
+            let incoming = &tributaries[y][x];
+            let highest = incoming.iter().copied().max().unwrap_or(0);
+            let expected = match incoming.iter().filter(|&&order| order == highest).count() {
+                _ if highest == 0 => 1,
+                n if n >= 2 => highest + 1,
+                _ => highest,
+            };
+            assert_eq!(cell.river_order, expected, "order at ({}, {})", x, y);
+            if expected > highest.max(1) {
+                confluences += 1;
+            }
+        }
+    }
+    assert!(confluences > 0, "no streams of equal order met");
+}
\ No newline at end of file
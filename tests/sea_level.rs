@@ -0,0 +1,43 @@
+//! Per-row sea level under the latitude water modes.
+
+mod common;
+
+use common::generate_world;
+use terrain_generator::{GenerationParams, WaterMode};
+
+#[test]
+fn polar_sea_level_is_highest_at_the_poles() {
+    let terrain = generate_world(GenerationParams { seed: 5, water_mode: WaterMode::Polar, ..GenerationParams::default() });
+    assert_eq!(terrain.sea_levels.len(), terrain.height as usize);
+    let equator = terrain.sea_level_at(terrain.height as usize / 2);
+    assert!(terrain.sea_level_at(0) > equator);
+    
+    let stats = terrain.stats().sea_level.expect("terrain has rows");
+    assert!(stats.min < stats.max);
+}
+
+#[test]
+fn elevation_mode_sea_level_is_flat() {
+    let terrain = generate_world(GenerationParams { seed: 5, water_mode: WaterMode::Elevation, ..GenerationParams::default() });
+    assert!(terrain.sea_levels.iter().all(|&level| level == terrain.sea_levels[0]));
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn profile_reports_the_sea_level_of_each_row() {
+    use terrain_generator::output::{export_profile_csv, ProfileLine};
+    
+    let terrain = generate_world(GenerationParams { seed: 5, water_mode: WaterMode::Polar, ..GenerationParams::default() });
+    let path = std::env::temp_dir().join("terrain_sea_level_profile.csv");
+    let line = ProfileLine { x0: 10, y0: 0, x1: 10, y1: terrain.height as usize - 1 };
+    export_profile_csv(&terrain, line, path.to_str().unwrap()).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    
+    for row in csv.lines().skip(1) {
+        let fields: Vec<&str> = row.split(',').collect();
+        let y: usize = fields[2].parse().unwrap();
+        let sea_level: f32 = fields[4].parse().unwrap();
+        assert_eq!(sea_level, terrain.sea_level_at(y));
+    }
+}
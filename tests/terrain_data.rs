@@ -0,0 +1,70 @@
+//! Queries on a finished `TerrainData`.
+
+mod common;
+
+use common::generate_world;
+use terrain_generator::terrain::TerrainGenerator;
+use terrain_generator::{GenerationParams, TerrainData};
+
+#[test]
+fn terrain_data_can_be_shared_between_threads() {
+    fn assert_sync<T: Sync + Send>() {}
+    assert_sync::<TerrainData>();
+}
+
+#[test]
+fn percentile_cache_is_refreshed_by_mutation() {
+    let mut terrain = generate_world(GenerationParams::default());
+    let median = terrain.elevation_at_percentile(50.0, false).unwrap();
+    
+    for (_, _, cell) in terrain.iter_cells_mut() {
+        cell.elevation += 1.0;
+    }
+    assert_eq!(terrain.elevation_at_percentile(50.0, false), Some(median + 1.0));
+}
+
+
+#[test]
+fn requested_all_land_or_all_water_is_not_degenerate() {
+    for water_percentage in [0.0, 100.0] {
+        let terrain = generate_world(GenerationParams { water_percentage, ..GenerationParams::default() });
+        assert!(!terrain.is_degenerate(), "{}% water reported as degenerate", water_percentage);
+    }
+}
+
+#[test]
+fn flooded_world_is_degenerate() {
+    let mut terrain = generate_world(GenerationParams::default());
+    for (_, _, cell) in terrain.iter_cells_mut() {
+        cell.is_water = true;
+    }
+    assert!(terrain.is_degenerate());
+}
+
+#[test]
+fn generator_reports_the_degenerate_check() {
+    let params = GenerationParams { degenerate_retries: 3, ..GenerationParams::default() };
+    let mut generator = TerrainGenerator::new(128, 128, params);
+    let terrain = generator.generate();
+    let reseeds = generator.degenerate_retries();
+    assert_eq!(reseeds.retries, 0);
+    assert!(!reseeds.degenerate);
+    assert_eq!(reseeds.water_percentage, terrain.water_percentage());
+}
+
+#[test]
+fn lakes_are_small_enclosed_bodies() {
+    let terrain = generate_world(GenerationParams { seed: 7, water_percentage: 50.0, ..GenerationParams::default() });
+    let max_lake = terrain.iter_cells().count() / 100;
+    assert!(terrain.water_bodies.iter().any(|body| body.is_lake));
+    for body in &terrain.water_bodies {
+        assert_eq!(body.is_lake, !body.is_connected_to_edge && body.size <= max_lake);
+    }
+    for (_, _, cell) in terrain.iter_cells() {
+        let lake = cell.water_body_id.is_some_and(|id| terrain.water_bodies[id].is_lake);
+        assert_eq!(cell.is_freshwater, lake);
+    }
+    
+    let largest = terrain.stats().largest_lake.expect("world has lakes");
+    assert!(terrain.water_bodies[largest.id].is_lake);
+}
\ No newline at end of file